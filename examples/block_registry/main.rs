@@ -0,0 +1,14 @@
+use rpg_baker::{behaviour::registry::BlockRegistry, project::Project};
+use std::path::Path;
+
+/// Prints the blocks a project can use as JSON, like an editor would read them for its palette.
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let path = Path::new("./examples/test_project").to_path_buf();
+    let project = Project::load(path)?;
+
+    let registry =
+        BlockRegistry::new(&project.plugin_database).with_custom_blocks(&project.resource_database);
+    println!("{}", serde_json::to_string_pretty(&registry)?);
+
+    Ok(())
+}
@@ -1,11 +1,35 @@
-use rpg_baker::project::{Project, resource::ResourceLoadError};
+use rpg_baker::project::{
+    Project,
+    resource::{ResourceData, ResourceLoadError},
+};
 use std::path::Path;
+use uuid::uuid;
 
 fn main() -> Result<(), ResourceLoadError> {
     let path = Path::new("./examples/test_project").to_path_buf();
     let project = Project::load(path)?;
 
-    project.run_from_start();
+    for error in project.plugin_database.errors() {
+        eprintln!("{}", error);
+    }
+    if let Some(error) = project.dependency_error() {
+        eprintln!("{}", error);
+    }
+
+    let potion = project
+        .resource_database
+        .get(&uuid!("9b2f6c1e-4d7a-4e58-b3c1-2a6f0d8e7c45"))?;
+    if let ResourceData::Custom(potion) = potion.lock_ref().data() {
+        println!("Potion: {:?}", potion.value());
+    }
+
+    for error in project.type_checker().check(&project.startup_behaviour) {
+        eprintln!("{}", error);
+    }
+
+    if let Err(error) = project.run_from_start() {
+        eprintln!("{}", error);
+    }
 
     Ok(())
 }
@@ -1,9 +1,12 @@
 use futures_signals::signal_vec::MutableVec;
 use rpg_baker::{
-    behaviour::{BehaviourDescriptor, BlockInstanceDescriptor, BlockScopeDescriptor},
+    behaviour::{
+        BehaviourDescriptor, BlockInstanceDescriptor, BlockScopeDescriptor,
+        event::{Event, EventHandlerDescriptor},
+    },
     project::{Project, resource::ResourceLoadError},
 };
-use std::path::Path;
+use std::path::PathBuf;
 
 macro_rules! make {
     ($type:ty => $content:tt) => {
@@ -12,8 +15,14 @@ macro_rules! make {
 }
 
 fn main() -> Result<(), ResourceLoadError> {
-    let path = Path::new("./examples/test_project").to_path_buf();
-    let mut project = Project::new(path).expect("Failed to create new project.");
+    // The project goes in the folder given as an argument, or in a temporary one, so that
+    // running this example never overwrites the test project.
+    let path = match std::env::args_os().nth(1) {
+        Some(path) => PathBuf::from(path),
+        None => std::env::temp_dir().join("rpg_baker_new_project"),
+    };
+    std::fs::create_dir_all(&path)?;
+    let mut project = Project::new(path.clone()).expect("Failed to create new project.");
 
     let add_two_numbers = make!(BlockInstanceDescriptor => {
         "source" : "builtin:change_screen",
@@ -21,12 +30,17 @@ fn main() -> Result<(), ResourceLoadError> {
     .unwrap();
 
     project.startup_behaviour = BehaviourDescriptor {
-        blocks: BlockScopeDescriptor {
-            blocks: MutableVec::new_with_values(vec![add_two_numbers]),
-        },
+        variables: Vec::new(),
+        handlers: vec![EventHandlerDescriptor {
+            blocks: BlockScopeDescriptor {
+                blocks: MutableVec::new_with_values(vec![add_two_numbers]),
+            },
+            ..EventHandlerDescriptor::new(Event::Created)
+        }],
     };
 
     project.save().expect("Failed to save!");
+    println!("Created a new project in {}", path.display());
 
     Ok(())
 }
@@ -0,0 +1,676 @@
+//! # Type checking
+//!
+//! Blocks declare the formats their slots accept and the format they return in a
+//! [`BlockSignature`]. The [`TypeChecker`] walks a [`BehaviourDescriptor`] and compares what fills
+//! every slot with what the slot accepts -- without evaluating anything -- so mistakes can be
+//! pointed out before the game runs.
+//!
+//! Signatures use [`Format::Parameter`]s to stand for formats that are only known once the block
+//! is used: in `ListItem`, the list is a `List<T>` and the block returns a `T`, where `T` is
+//! whatever the items of the list turn out to be. Two parameter names are special:
+//! - [`NUMBER`] accepts an `Int` or a `Float`. It becomes a `Float` if any of the values it was
+//!   bound to is a float, just like arithmetic does when the behaviour runs.
+//! - [`PAYLOAD`] is the format of the payload of the event being handled.
+//!
+//! Builtin blocks are always known. The signatures of plugin and custom blocks are read from a
+//! [`BlockRegistry`] given to the checker; without one, only the blocks inside of them are checked.
+//!
+//! Whenever a format can't be known (like the fields of a tuple built by blocks, or a format
+//! defined in another resource), the checker gives the benefit of the doubt.
+
+use super::{
+    BehaviourDescriptor, BlockContentDescriptor, BlockInstanceDescriptor, BlockSlotDescriptor,
+    BlockSlotRef, BlockSourceDescriptor, ReifyError,
+    custom::CustomBlockDefinition,
+    registry::{BlockMetadata, BlockRegistry},
+    variables::{VariableRef, VariableScope, Variables},
+};
+use crate::format::{BaseType, Format, VariantValue};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt::{Display, Formatter},
+};
+use thiserror::Error;
+
+/// The name of the parameter that accepts either an `Int` or a `Float`.
+pub const NUMBER: &str = "number";
+/// The name of the parameter that stands for the payload of the event being handled.
+pub const PAYLOAD: &str = "payload";
+/// The name of the parameter that stands for a format that can't be known before running.
+pub const UNKNOWN: &str = "unknown";
+
+/// The formats a block accepts in each of its slots and variables, and the format it returns.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockSignature {
+    /// The format each slot or variable of the block accepts, by name.
+    pub inputs: Vec<(Cow<'static, str>, Format)>,
+    /// The format of the value the block returns.
+    pub output: Format,
+}
+
+impl BlockSignature {
+    /// Returns the format a slot or variable accepts.
+    pub fn input(&self, name: &str) -> Option<&Format> {
+        self.inputs
+            .iter()
+            .find(|(input, _)| *input == name)
+            .map(|(_, format)| format)
+    }
+}
+
+/// Shorthands for the formats used in block signatures.
+pub mod formats {
+    use super::{NUMBER, UNKNOWN};
+    use crate::format::{BaseType, Format};
+
+    pub fn void() -> Format {
+        Format::BaseType(BaseType::Void)
+    }
+
+    pub fn int() -> Format {
+        Format::BaseType(BaseType::Int)
+    }
+
+    pub fn float() -> Format {
+        Format::BaseType(BaseType::Float)
+    }
+
+    pub fn text() -> Format {
+        Format::BaseType(BaseType::Text)
+    }
+
+    pub fn truth() -> Format {
+        Format::BaseType(BaseType::Truth)
+    }
+
+    /// An `Int` or a `Float`.
+    pub fn number() -> Format {
+        Format::Parameter(NUMBER.into())
+    }
+
+    /// A format that is only known once the block is used.
+    pub fn param(name: &str) -> Format {
+        Format::Parameter(name.into())
+    }
+
+    /// A format that can't be known before running.
+    pub fn unknown() -> Format {
+        Format::Parameter(UNKNOWN.into())
+    }
+
+    pub fn list(item: Format) -> Format {
+        Format::List(Box::new(item))
+    }
+
+    pub fn maybe(item: Format) -> Format {
+        Format::Maybe(Box::new(item))
+    }
+}
+
+/// A step on the way from a behaviour to one of the blocks inside of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckPathSegment {
+    /// An event handler of the behaviour.
+    Handler(usize),
+    /// A block in a scope (a handler or a body).
+    Block(usize),
+    /// A slot, variable or body of a block.
+    Content(String),
+}
+
+/// The path from a behaviour to one of the slots inside of it, like `handlers[0][2].a.then[1]`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CheckPath(pub Vec<CheckPathSegment>);
+
+impl Display for CheckPath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for segment in &self.0 {
+            match segment {
+                CheckPathSegment::Handler(index) => write!(f, "handlers[{}]", index)?,
+                CheckPathSegment::Block(index) => write!(f, "[{}]", index)?,
+                CheckPathSegment::Content(name) => write!(f, ".{}", name)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Something that fills a slot which doesn't fit it, and where it was found.
+#[derive(Debug, Error)]
+#[error("{path}: {problem}")]
+pub struct TypeError {
+    pub path: CheckPath,
+    pub problem: TypeProblem,
+}
+
+/// The reason why something doesn't fit a slot.
+#[derive(Debug, Error)]
+pub enum TypeProblem {
+    #[error("expected {expected}, found {found}")]
+    Mismatched { expected: Format, found: Format },
+    #[error("missing `{0}`")]
+    Missing(String),
+    #[error("variable `{0}` was not declared")]
+    UndeclaredVariable(String),
+}
+
+/// Checks that what fills the slots of blocks fits them, without running anything.
+///
+/// Local variables are always known from the behaviour being checked. Global and object
+/// variables are only checked if they were given to the checker.
+#[derive(Debug, Default, Clone)]
+pub struct TypeChecker {
+    scopes: HashMap<VariableScope, Variables>,
+    /// The blocks whose signatures are known, besides the builtin ones.
+    registry: Option<BlockRegistry>,
+}
+
+impl TypeChecker {
+    /// Creates a checker that only knows about local variables.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Lets the checker know the variables declared in a scope.
+    pub fn with_variables(mut self, scope: VariableScope, variables: Variables) -> Self {
+        self.scopes.insert(scope, variables);
+        self
+    }
+
+    /// Lets the checker know the signatures of plugin and custom blocks.
+    pub fn with_registry(mut self, registry: BlockRegistry) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Checks every handler of a behaviour, returning every type mismatch found.
+    pub fn check(&self, behaviour: &BehaviourDescriptor) -> Vec<TypeError> {
+        let mut walker = self.walker(Variables::from(behaviour.variables.as_slice()));
+        for (index, handler) in behaviour.handlers.iter().enumerate() {
+            walker.payload = Some(handler.payload_format());
+            walker.path.push(CheckPathSegment::Handler(index));
+            walker.scope(handler.blocks.blocks.lock_ref().iter());
+            walker.path.pop();
+        }
+        walker.errors
+    }
+
+    /// Checks the body of a custom block, with its parameters and variables as local variables,
+    /// returning every type mismatch found.
+    pub fn check_custom_block(&self, definition: &CustomBlockDefinition) -> Vec<TypeError> {
+        let locals = definition
+            .parameters
+            .iter()
+            .chain(&definition.variables)
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut walker = self.walker(Variables::from(locals.as_slice()));
+        walker.scope(definition.body.blocks.lock_ref().iter());
+        walker.errors
+    }
+
+    fn walker(&self, locals: Variables) -> Walker<'_> {
+        let mut scopes = self.scopes.clone();
+        scopes.insert(VariableScope::Local, locals);
+        Walker {
+            scopes,
+            registry: self.registry.as_ref(),
+            payload: None,
+            path: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+}
+
+impl BehaviourDescriptor {
+    /// Checks every handler of this behaviour, knowing only its local variables.
+    pub fn check(&self) -> Vec<TypeError> {
+        TypeChecker::new().check(self)
+    }
+}
+
+impl CustomBlockDefinition {
+    /// Checks the body of this block, knowing only its parameters and local variables.
+    pub fn check(&self) -> Vec<TypeError> {
+        TypeChecker::new().check_custom_block(self)
+    }
+}
+
+struct Walker<'c> {
+    scopes: HashMap<VariableScope, Variables>,
+    registry: Option<&'c BlockRegistry>,
+    payload: Option<Format>,
+    path: Vec<CheckPathSegment>,
+    errors: Vec<TypeError>,
+}
+
+impl Walker<'_> {
+    /// Returns the signature of the block a descriptor describes, if it's known.
+    fn signature_of(&self, descriptor: &BlockInstanceDescriptor) -> Option<BlockSignature> {
+        match &descriptor.source {
+            BlockSourceDescriptor::Builtin(builtin) => Some(builtin.signature()),
+            source => self.registry?.get(source).map(BlockMetadata::signature),
+        }
+    }
+
+    fn report(&mut self, problem: TypeProblem) {
+        self.errors.push(TypeError {
+            path: CheckPath(self.path.clone()),
+            problem,
+        });
+    }
+
+    fn scope<'a>(&mut self, blocks: impl IntoIterator<Item = &'a BlockInstanceDescriptor>) {
+        for (index, block) in blocks.into_iter().enumerate() {
+            self.path.push(CheckPathSegment::Block(index));
+            self.block(block);
+            self.path.pop();
+        }
+    }
+
+    /// Checks a block and everything inside of it, returning the format it returns.
+    fn block(&mut self, descriptor: &BlockInstanceDescriptor) -> Format {
+        let mut bindings = Bindings::default();
+        if let Some(payload) = &self.payload {
+            bindings.0.insert(PAYLOAD.into(), payload.clone());
+        }
+
+        let Some(signature) = self.signature_of(descriptor) else {
+            // Without a signature, only the blocks inside can be checked.
+            for (name, content) in &descriptor.content {
+                self.path.push(CheckPathSegment::Content(name.clone()));
+                self.content(content);
+                self.path.pop();
+            }
+            return formats::unknown();
+        };
+
+        // Variables go first, so the formats they were declared with bind the parameters.
+        let (variables, slots): (Vec<_>, Vec<_>) =
+            signature.inputs.iter().partition(|(name, _)| {
+                matches!(
+                    descriptor.content.get(name.as_ref()),
+                    Some(BlockContentDescriptor::Variable(_))
+                )
+            });
+        for (name, expected) in variables.into_iter().chain(slots) {
+            self.path.push(CheckPathSegment::Content(name.to_string()));
+            match descriptor.content.get(name.as_ref()) {
+                Some(content) => {
+                    if let Some(found) = self.content(content)
+                        && !bindings.fit(expected, &found)
+                    {
+                        // Numbers accept floats too, so whatever they were bound to isn't the whole story.
+                        let expected = match expected {
+                            Format::Parameter(name) if name == NUMBER => expected.clone(),
+                            _ => bindings.apply(expected),
+                        };
+                        self.report(TypeProblem::Mismatched { expected, found });
+                    }
+                }
+                None => self.report(TypeProblem::Missing(name.to_string())),
+            }
+            self.path.pop();
+        }
+
+        for (name, content) in &descriptor.content {
+            if signature.input(name).is_none() {
+                self.path.push(CheckPathSegment::Content(name.clone()));
+                self.content(content);
+                self.path.pop();
+            }
+        }
+
+        erase(&bindings.apply(&signature.output))
+    }
+
+    /// Checks what fills a block, returning its format if it's a slot or a variable.
+    fn content(&mut self, content: &BlockContentDescriptor) -> Option<Format> {
+        match content {
+            BlockContentDescriptor::Slot(BlockSlotDescriptor::VariantValue(value)) => {
+                Some(format_of(value))
+            }
+            BlockContentDescriptor::Slot(BlockSlotDescriptor::Block(block)) => {
+                Some(self.block(block))
+            }
+            BlockContentDescriptor::Variable(variable) => self.variable(variable),
+            BlockContentDescriptor::Part(part) => {
+                if let Some(body) = &part.body {
+                    self.scope(body);
+                }
+                None
+            }
+        }
+    }
+
+    /// Returns the format a variable was declared with, if its scope is known.
+    fn variable(&mut self, variable: &VariableRef) -> Option<Format> {
+        let variables = self.scopes.get(&variable.scope)?;
+        match variables.format(&variable.variable) {
+            Some(format) => Some(format.clone()),
+            None => {
+                self.report(TypeProblem::UndeclaredVariable(variable.variable.clone()));
+                Some(formats::unknown())
+            }
+        }
+    }
+}
+
+/// The formats the parameters of a signature were bound to while checking a block.
+#[derive(Debug, Default)]
+struct Bindings(HashMap<String, Format>);
+
+impl Bindings {
+    /// Returns whether something of the `found` format fits where `expected` is accepted,
+    /// binding the parameters of `expected` on the way.
+    fn fit(&mut self, expected: &Format, found: &Format) -> bool {
+        match (expected, found) {
+            // Nothing can be said about something whose format isn't known.
+            (_, Format::Parameter(_)) => true,
+            (Format::Parameter(name), Format::BaseType(found)) if name == NUMBER => {
+                match (self.0.get(NUMBER), found) {
+                    (_, BaseType::Float) => {
+                        self.0.insert(NUMBER.into(), formats::float());
+                        true
+                    }
+                    (None, BaseType::Int) => {
+                        self.0.insert(NUMBER.into(), formats::int());
+                        true
+                    }
+                    (Some(_), BaseType::Int) => true,
+                    _ => false,
+                }
+            }
+            (Format::Parameter(name), _) if name == NUMBER => false,
+            (Format::Parameter(name), found) => match self.0.get(name).cloned() {
+                Some(bound) => self.fit(&bound, found),
+                None => {
+                    self.0.insert(name.clone(), found.clone());
+                    true
+                }
+            },
+            // Integers are turned into floats wherever floats are expected.
+            (Format::BaseType(BaseType::Float), Format::BaseType(BaseType::Int)) => true,
+            (Format::BaseType(expected), Format::BaseType(found)) => expected == found,
+            (Format::List(expected), Format::List(found))
+            | (Format::Maybe(expected), Format::Maybe(found))
+            | (Format::Map(expected), Format::Map(found)) => self.fit(expected, found),
+            // Maps are held in tuples, one field for each key.
+            (Format::Map(expected), Format::Tuple(found)) => {
+                found.iter().all(|(_, found)| self.fit(expected, found))
+            }
+            (Format::Tuple(expected), Format::Tuple(found))
+            | (Format::Either(expected), Format::Either(found)) => {
+                expected.len() == found.len()
+                    && expected.iter().all(|(name, expected)| {
+                        found
+                            .iter()
+                            .find(|(other, _)| other == name)
+                            .is_some_and(|(_, found)| self.fit(expected, found))
+                    })
+            }
+            (Format::External(expected), Format::External(found)) => expected == found,
+            (
+                Format::Generic {
+                    format: expected,
+                    arguments: expected_arguments,
+                },
+                Format::Generic {
+                    format: found,
+                    arguments: found_arguments,
+                },
+            ) => {
+                expected == found
+                    && expected_arguments.len() == found_arguments.len()
+                    && expected_arguments
+                        .iter()
+                        .zip(found_arguments)
+                        .all(|(expected, found)| self.fit(expected, found))
+            }
+            // Formats defined in other resources can't be looked at without loading them.
+            (Format::External(_) | Format::Generic { .. }, _)
+            | (_, Format::External(_) | Format::Generic { .. }) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns a format with its bound parameters replaced by what they were bound to.
+    fn apply(&self, format: &Format) -> Format {
+        let apply_all = |formats: &[(String, Format)]| {
+            formats
+                .iter()
+                .map(|(name, format)| (name.clone(), self.apply(format)))
+                .collect()
+        };
+
+        match format {
+            Format::Parameter(name) => self.0.get(name).cloned().unwrap_or(format.clone()),
+            Format::Tuple(fields) => Format::Tuple(apply_all(fields)),
+            Format::Either(cases) => Format::Either(apply_all(cases)),
+            Format::List(item) => Format::List(Box::new(self.apply(item))),
+            Format::Maybe(item) => Format::Maybe(Box::new(self.apply(item))),
+            Format::Map(item) => Format::Map(Box::new(self.apply(item))),
+            Format::Generic { format, arguments } => Format::Generic {
+                format: format.clone(),
+                arguments: arguments
+                    .iter()
+                    .map(|argument| self.apply(argument))
+                    .collect(),
+            },
+            Format::BaseType(_) | Format::External(_) => format.clone(),
+        }
+    }
+}
+
+/// Replaces the parameters left in a format with [`UNKNOWN`], so they aren't mistaken for the
+/// parameters of another signature.
+fn erase(format: &Format) -> Format {
+    let erase_all = |formats: &[(String, Format)]| {
+        formats
+            .iter()
+            .map(|(name, format)| (name.clone(), erase(format)))
+            .collect()
+    };
+
+    match format {
+        Format::Parameter(_) => formats::unknown(),
+        Format::Tuple(fields) => Format::Tuple(erase_all(fields)),
+        Format::Either(cases) => Format::Either(erase_all(cases)),
+        Format::List(item) => formats::list(erase(item)),
+        Format::Maybe(item) => formats::maybe(erase(item)),
+        Format::Map(item) => Format::Map(Box::new(erase(item))),
+        Format::Generic { format, arguments } => Format::Generic {
+            format: format.clone(),
+            arguments: arguments.iter().map(erase).collect(),
+        },
+        Format::BaseType(_) | Format::External(_) => format.clone(),
+    }
+}
+
+/// Returns the format of a value, as far as it can be told from the value alone.
+pub fn format_of(value: &VariantValue) -> Format {
+    match value {
+        VariantValue::Void => formats::void(),
+        VariantValue::Int(_) => formats::int(),
+        VariantValue::Float(_) => formats::float(),
+        VariantValue::Truth(_) => formats::truth(),
+        VariantValue::Text(_) => formats::text(),
+        VariantValue::List(items) => formats::list(
+            items
+                .first()
+                .map(format_of)
+                .unwrap_or_else(formats::unknown),
+        ),
+        VariantValue::Tuple { fields } => Format::Tuple(
+            fields
+                .iter()
+                .map(|(name, value)| (name.clone(), format_of(value)))
+                .collect(),
+        ),
+        VariantValue::Maybe { maybe } => formats::maybe(
+            maybe
+                .as_deref()
+                .map(format_of)
+                .unwrap_or_else(formats::unknown),
+        ),
+        VariantValue::Case { .. } | VariantValue::Resource { .. } => formats::unknown(),
+    }
+}
+
+/// Checks that a value written directly in a slot fits what the slot accepts.
+pub fn check_value(
+    signature: &BlockSignature,
+    slot: &'static str,
+    value: &VariantValue,
+) -> Result<(), ReifyError<'static>> {
+    let Some(expected) = signature.input(slot) else {
+        return Ok(());
+    };
+    if Bindings::default().fit(expected, &format_of(value)) {
+        Ok(())
+    } else {
+        Err(ReifyError::MismatchedType(
+            BlockSlotRef(slot.into()),
+            expected.clone(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::behaviour::registry::RegisteredBlock;
+    use crate::plugin::BlockContributionRef;
+    use serde_json::{Value, json};
+    use uuid::{Uuid, uuid};
+
+    const HALF: Uuid = uuid!("6a1f0c3e-2b7d-4e59-8c14-93d5e0a7b2f6");
+
+    fn behaviour(blocks: Value) -> BehaviourDescriptor {
+        serde_json::from_value(json!({
+            "variables": [
+                { "name": "i", "format": { "type": "base_type", "content": "int" } },
+                { "name": "f", "format": { "type": "base_type", "content": "float" } },
+                { "name": "t", "format": { "type": "base_type", "content": "text" } }
+            ],
+            "handlers": [
+                { "event": "created", "blocks": [] },
+                { "event": "tick", "blocks": blocks }
+            ]
+        }))
+        .unwrap()
+    }
+
+    fn set(variable: &str, value: Value) -> Value {
+        json!({
+            "source": "builtin:set_variable",
+            "variable": { "variable": variable, "scope": "local" },
+            "value": value
+        })
+    }
+
+    /// A checker that knows a plugin block and a custom block, both taking a `number`.
+    fn checker() -> TypeChecker {
+        let number = json!({ "type": "parameter", "content": "number" });
+        let twice = serde_json::from_value(json!({
+            "category": "operators",
+            "phrase": "twice {n}",
+            "description": "",
+            "contents": [{ "name": "n", "kind": "slot", "format": number, "default": 0 }],
+            "output": number
+        }))
+        .unwrap();
+        let half: CustomBlockDefinition = serde_json::from_value(json!({
+            "name": "Half",
+            "phrase": "half of {n}",
+            "parameters": [{ "name": "n", "format": number }],
+            "output": number
+        }))
+        .unwrap();
+
+        let mut registry = BlockRegistry::builtin();
+        registry.blocks.push(RegisteredBlock {
+            id: BlockSourceDescriptor::Plugin(BlockContributionRef {
+                plugin_id: "math".into(),
+                block_id: "twice".into(),
+            }),
+            metadata: twice,
+        });
+        registry.blocks.push(RegisteredBlock {
+            id: BlockSourceDescriptor::Custom(HALF),
+            metadata: half.metadata(),
+        });
+        TypeChecker::new().with_registry(registry)
+    }
+
+    fn problems(checker: &TypeChecker, blocks: Value) -> Vec<String> {
+        checker
+            .check(&behaviour(blocks))
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn ints_can_be_assigned_to_floats() {
+        let checker = TypeChecker::new();
+        assert!(problems(&checker, json!([set("f", json!(1))])).is_empty());
+        assert_eq!(
+            problems(&checker, json!([set("f", json!(1)), set("i", json!(1.5))])),
+            ["handlers[1][1].value: expected Int, found Float"]
+        );
+    }
+
+    #[test]
+    fn number_slots_of_plugin_and_custom_blocks_are_bound() {
+        let checker = checker();
+        let twice = |n: Value| json!({ "source": "math:twice", "n": n });
+        let half = |n: Value| json!({ "source": format!("custom:{HALF}"), "n": n });
+
+        assert!(
+            problems(
+                &checker,
+                json!([
+                    set("i", twice(json!(2))),
+                    set("f", twice(json!(2.5))),
+                    set("f", half(json!(3))),
+                ])
+            )
+            .is_empty()
+        );
+        assert_eq!(
+            problems(
+                &checker,
+                json!([set("i", twice(json!(2.5))), set("t", half(json!(3)))])
+            ),
+            [
+                "handlers[1][0].value: expected Int, found Float",
+                "handlers[1][1].value: expected Text, found Int",
+            ]
+        );
+        assert_eq!(
+            problems(&checker, json!([set("i", twice(json!("two")))])),
+            ["handlers[1][0].value.n: expected number, found Text"]
+        );
+    }
+
+    #[test]
+    fn missing_slots_and_undeclared_variables_are_reported() {
+        let checker = TypeChecker::new();
+        assert_eq!(
+            problems(
+                &checker,
+                json!([{
+                    "source": "builtin:set_variable",
+                    "variable": { "variable": "i", "scope": "local" }
+                }])
+            ),
+            ["handlers[1][0].value: missing `value`"]
+        );
+        assert_eq!(
+            problems(&checker, json!([set("nope", json!(1))])),
+            ["handlers[1][0].variable: variable `nope` was not declared"]
+        );
+    }
+}
@@ -0,0 +1,203 @@
+//! # Composite blocks
+//!
+//! Blocks that are made out of other blocks, instead of Rust code.
+//!
+//! A composite block runs its body with its own local variables: one for each of its slots
+//! (holding what the slot evaluated to) and one for each variable it declares. Once the body is
+//! done, the block returns whatever its result evaluates to -- or, if a `Return` block stopped
+//! it early, what was returned.
+//!
+//! The body is only reified the first time the block runs, so composite blocks can use
+//! themselves. To keep a block that uses itself forever from crashing the game, composite blocks
+//! can only run [`MAX_DEPTH`] levels inside each other.
+
+use super::{
+    BlockContentDescriptor, BlockInstanceDescriptor, BlockScope, BlockSlot, BlockSlotDescriptor,
+    BlockSlotRef, ExecutionContext, ReifyContext, ReifyError, RuntimeError, TypedBlock,
+    registry::{BlockContentKind, BlockMetadata},
+    variables::{VariableDeclaration, Variables},
+};
+use crate::format::VariantValue;
+use serde::{Deserialize, Serialize};
+use std::cell::OnceCell;
+
+/// How many composite blocks can run inside each other before the behaviour is stopped.
+pub const MAX_DEPTH: usize = 256;
+
+/// Describes what a composite block does.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompositeBlockDefinition {
+    /// Local variables of the block, besides the ones holding its slots.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub variables: Vec<VariableDeclaration>,
+    /// The blocks that run whenever the block is evaluated.
+    #[serde(default)]
+    pub body: Vec<BlockInstanceDescriptor>,
+    /// What the block returns, evaluated after its body. Without it, the block returns nothing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<BlockSlotDescriptor>,
+}
+
+/// A block made out of other blocks.
+pub struct CompositeBlock {
+    definition: CompositeBlockDefinition,
+    /// The local variables the slots are stored in.
+    parameters: Vec<VariableDeclaration>,
+    slots: Vec<(String, BlockSlot)>,
+    /// The body and result, once they're reified.
+    reified: OnceCell<(BlockScope, BlockSlot)>,
+}
+
+impl CompositeBlock {
+    /// Creates a composite block from its definition and a descriptor that fills its slots.
+    ///
+    /// Slots that aren't filled by the descriptor hold their default value.
+    pub fn from_descriptor(
+        metadata: &BlockMetadata,
+        definition: &CompositeBlockDefinition,
+        descriptor: &BlockInstanceDescriptor,
+        ctx: &ReifyContext,
+    ) -> Result<Self, ReifyError<'static>> {
+        let mut parameters = Vec::new();
+        let mut slots = Vec::new();
+
+        for content in &metadata.contents {
+            let BlockContentKind::Slot { format, default } = &content.kind else {
+                continue;
+            };
+            let slot = match descriptor.content.get(&content.name) {
+                Some(BlockContentDescriptor::Slot(slot)) => slot.reify(ctx).map_err(|e| {
+                    ReifyError::Child(BlockSlotRef(content.name.clone().into()), Box::new(e))
+                })?,
+                Some(_) => {
+                    return Err(ReifyError::ShouldBeASlot(BlockSlotRef(
+                        content.name.clone().into(),
+                    )));
+                }
+                None => BlockSlot::new_with_value(default.clone()),
+            };
+            parameters.push(VariableDeclaration {
+                name: content.name.clone(),
+                format: format.clone(),
+            });
+            slots.push((content.name.clone(), slot));
+        }
+
+        Ok(CompositeBlock {
+            definition: definition.clone(),
+            parameters,
+            slots,
+            reified: OnceCell::new(),
+        })
+    }
+
+    /// Returns the body and result of the block, reifying them if they weren't already.
+    fn reified(&self, ctx: &ReifyContext) -> Result<&(BlockScope, BlockSlot), ReifyError<'static>> {
+        if let Some(reified) = self.reified.get() {
+            return Ok(reified);
+        }
+
+        let body = BlockScope::from_descriptors(&self.definition.body, ctx)?;
+        let result = match &self.definition.result {
+            Some(result) => result
+                .reify(ctx)
+                .map_err(|e| ReifyError::Child(BlockSlotRef("result".into()), Box::new(e)))?,
+            None => BlockSlot::new(),
+        };
+        Ok(self.reified.get_or_init(|| (body, result)))
+    }
+}
+
+impl TypedBlock for CompositeBlock {
+    fn evaluate(&self, ctx: &mut ExecutionContext) -> Result<VariantValue, RuntimeError> {
+        if ctx.depth >= MAX_DEPTH {
+            return Err(RuntimeError::TooDeep(MAX_DEPTH));
+        }
+
+        let project = ctx.game.project;
+        let reify_ctx = ReifyContext::for_project(project);
+        let (body, result) = self
+            .reified(&reify_ctx)
+            .map_err(|error| RuntimeError::Reify(Box::new(error)))?;
+
+        // Slots are evaluated by the caller, with the caller's variables.
+        let mut locals = Variables::from_declarations(
+            self.parameters
+                .iter()
+                .chain(&self.definition.variables)
+                .map(|declaration| (declaration.name.as_str(), &declaration.format)),
+        );
+        for (name, slot) in &self.slots {
+            let value = slot.just_evaluate(ctx)?;
+            locals.set(name, value, &ctx.game.project.resource_database)?;
+        }
+
+        let caller_locals = std::mem::replace(&mut ctx.locals, locals);
+        ctx.depth += 1;
+        let value = body.evaluate(ctx).and_then(|_| match ctx.returned.take() {
+            Some(value) => Ok(value),
+            None => result.just_evaluate(ctx),
+        });
+        ctx.depth -= 1;
+        ctx.locals = caller_locals;
+        // A `Return` inside of the block only stops the block itself.
+        ctx.returned = None;
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::{Project, runtime::Game};
+    use serde_json::json;
+
+    #[test]
+    fn number_slots_take_ints_and_floats() {
+        let temp = tempfile::tempdir().unwrap();
+        Project::new(temp.path().to_path_buf()).unwrap();
+        let plugin = temp.path().join("plugins").join("math");
+        std::fs::create_dir_all(&plugin).unwrap();
+        let number = json!({ "type": "parameter", "content": "number" });
+        let manifest = json!({
+            "name": "Math",
+            "id": "math",
+            "description": "",
+            "version": "0.1.0",
+            "authors": [],
+            "plugin_contributions": {
+                "blocks": [{
+                    "id": "twice",
+                    "category": "operators",
+                    "phrase": "twice {n}",
+                    "description": "",
+                    "contents": [
+                        { "name": "n", "kind": "slot", "format": number, "default": 0 }
+                    ],
+                    "output": number,
+                    "definition": {
+                        "result": {
+                            "source": "builtin:add",
+                            "a": { "source": "builtin:get_variable", "variable": { "variable": "n", "scope": "local" } },
+                            "b": { "source": "builtin:get_variable", "variable": { "variable": "n", "scope": "local" } }
+                        }
+                    }
+                }]
+            }
+        });
+        std::fs::write(plugin.join("plugin.json"), manifest.to_string()).unwrap();
+
+        let project = Project::load_with(temp.path().to_path_buf(), None).unwrap();
+        let ctx = ReifyContext::for_project(&project);
+        let mut game = Game::from_project(&project);
+        let mut twice = |n: serde_json::Value| {
+            let descriptor: BlockInstanceDescriptor =
+                serde_json::from_value(json!({ "source": "math:twice", "n": n })).unwrap();
+            let block = descriptor.reify(&ctx).unwrap();
+            block.evaluate(&mut ExecutionContext::new(&mut game))
+        };
+
+        assert_eq!(twice(json!(21)).unwrap(), VariantValue::Int(42));
+        assert_eq!(twice(json!(0.25)).unwrap(), VariantValue::Float(0.5));
+    }
+}
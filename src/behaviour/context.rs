@@ -0,0 +1,113 @@
+//! The state a block can see and touch while it's being evaluated.
+
+use super::{
+    RuntimeError,
+    variables::{VariableRef, VariableScope, Variables},
+};
+use crate::{
+    format::VariantValue,
+    project::{object::ObjectInstance, runtime::Game},
+};
+
+/// Everything a running behaviour has access to: the game, the object it belongs to,
+/// its own local variables and the payload of the event that started it.
+///
+/// A context is created once per behaviour run and passed, mutably, through every
+/// [`super::TypedBlock::evaluate`] call.
+pub struct ExecutionContext<'ctx, 'game> {
+    /// The game that is running the behaviour.
+    pub game: &'ctx mut Game<'game>,
+    /// The object that owns the running behaviour, if it belongs to one.
+    pub object: Option<&'ctx mut ObjectInstance<'game>>,
+    /// Variables that only live for this run of the behaviour.
+    pub locals: Variables,
+    /// The data that came along with the event that started this run.
+    pub payload: VariantValue,
+    /// The value a `Return` block returned, while the blocks around it are being stopped.
+    pub returned: Option<VariantValue>,
+    /// How many custom blocks are running inside each other.
+    pub depth: usize,
+    /// How many times loops went around during this run.
+    pub iterations: usize,
+}
+
+impl<'ctx, 'game> ExecutionContext<'ctx, 'game> {
+    /// Creates a context for a behaviour that doesn't belong to any object
+    /// and wasn't started with any payload.
+    pub fn new(game: &'ctx mut Game<'game>) -> Self {
+        Self {
+            game,
+            object: None,
+            locals: Variables::default(),
+            payload: VariantValue::Void,
+            returned: None,
+            depth: 0,
+            iterations: 0,
+        }
+    }
+
+    /// Sets the object that owns the running behaviour.
+    pub fn with_object(mut self, object: &'ctx mut ObjectInstance<'game>) -> Self {
+        self.object = Some(object);
+        self
+    }
+
+    /// Sets the payload of the event that started the running behaviour.
+    pub fn with_payload(mut self, payload: VariantValue) -> Self {
+        self.payload = payload;
+        self
+    }
+
+    /// Returns whether a `Return` block ran, and the blocks around it should stop.
+    pub fn is_returning(&self) -> bool {
+        self.returned.is_some()
+    }
+
+    /// Returns the variables that live in a scope.
+    pub fn variables(&self, scope: VariableScope) -> Result<&Variables, RuntimeError> {
+        match scope {
+            VariableScope::Global => Ok(&self.game.story),
+            VariableScope::Object => self
+                .object
+                .as_deref()
+                .map(|object| &object.variables)
+                .ok_or(RuntimeError::NoOwningObject),
+            VariableScope::Local => Ok(&self.locals),
+        }
+    }
+
+    /// Returns the variables that live in a scope, mutably.
+    pub fn variables_mut(&mut self, scope: VariableScope) -> Result<&mut Variables, RuntimeError> {
+        match scope {
+            VariableScope::Global => Ok(&mut self.game.story),
+            VariableScope::Object => self
+                .object
+                .as_deref_mut()
+                .map(|object| &mut object.variables)
+                .ok_or(RuntimeError::NoOwningObject),
+            VariableScope::Local => Ok(&mut self.locals),
+        }
+    }
+
+    /// Returns the current value of a variable.
+    pub fn get_variable(&self, variable: &VariableRef) -> Result<VariantValue, RuntimeError> {
+        self.variables(variable.scope)?
+            .get(&variable.variable)
+            .cloned()
+            .ok_or_else(|| RuntimeError::UndeclaredVariable(variable.variable.clone()))
+    }
+
+    /// Changes the value of a variable.
+    pub fn set_variable(
+        &mut self,
+        variable: &VariableRef,
+        value: VariantValue,
+    ) -> Result<(), RuntimeError> {
+        let project = self.game.project;
+        self.variables_mut(variable.scope)?.set(
+            &variable.variable,
+            value,
+            &project.resource_database,
+        )
+    }
+}
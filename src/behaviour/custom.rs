@@ -0,0 +1,101 @@
+//! # Custom blocks
+//!
+//! Blocks defined inside of a project, as resources, out of other blocks. They are used by
+//! behaviours just like builtin blocks, with a `custom:<uuid>` source.
+//!
+//! Custom blocks run as [`CompositeBlock`]s: their parameters are the slots of the block, and
+//! they hand a value back with a `Return` block.
+
+use super::{
+    BlockInstanceDescriptor, BlockScopeDescriptor, ReifyContext, ReifyError, TypedBlock,
+    composite::{CompositeBlock, CompositeBlockDefinition},
+    registry::{BlockCategory, BlockContentKind, BlockContentMetadata, BlockMetadata},
+    variables::VariableDeclaration,
+};
+use crate::{
+    format::{BaseType, Format},
+    project::resource::{ResourceData, ResourceLoadError},
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A resource that defines a block out of other blocks.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomBlockDefinition {
+    pub name: String,
+    /// How the block reads, with its parameters in braces, like `"heal {target} by {amount}"`.
+    pub phrase: String,
+    #[serde(default)]
+    pub description: String,
+    /// The slots of the block, which its body reads as local variables.
+    #[serde(default)]
+    pub parameters: Vec<VariableDeclaration>,
+    /// The format of the value the block returns.
+    #[serde(default = "void")]
+    pub output: Format,
+    /// Local variables of the block, besides its parameters.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub variables: Vec<VariableDeclaration>,
+    /// The blocks that run whenever the block is evaluated.
+    #[serde(default)]
+    pub body: BlockScopeDescriptor,
+}
+
+fn void() -> Format {
+    Format::BaseType(BaseType::Void)
+}
+
+impl CustomBlockDefinition {
+    /// Describes this block for editors.
+    pub fn metadata(&self) -> BlockMetadata {
+        BlockMetadata {
+            category: BlockCategory::Other,
+            phrase: self.phrase.clone(),
+            description: self.description.clone(),
+            contents: self
+                .parameters
+                .iter()
+                .map(|parameter| BlockContentMetadata {
+                    name: parameter.name.clone(),
+                    kind: BlockContentKind::Slot {
+                        format: parameter.format.clone(),
+                        default: parameter.format.default_value(),
+                    },
+                })
+                .collect(),
+            output: self.output.clone(),
+        }
+    }
+
+    /// Returns what runs when this block is evaluated.
+    pub fn composite(&self) -> CompositeBlockDefinition {
+        CompositeBlockDefinition {
+            variables: self.variables.clone(),
+            body: self.body.blocks.lock_ref().to_vec(),
+            result: None,
+        }
+    }
+}
+
+/// Creates the custom block defined by a resource from a descriptor that fills its slots.
+pub fn reify_custom_block(
+    uuid: Uuid,
+    descriptor: &BlockInstanceDescriptor,
+    ctx: &ReifyContext,
+) -> Result<Box<dyn TypedBlock>, ReifyError<'static>> {
+    let resource = match ctx.resources {
+        Some(resources) => resources.get(&uuid),
+        None => Err(ResourceLoadError::DoesNotExist),
+    }
+    .map_err(|error| ReifyError::UnloadableCustomBlock(uuid, error))?;
+
+    match resource.lock_ref().data() {
+        ResourceData::Block(definition) => Ok(Box::new(CompositeBlock::from_descriptor(
+            &definition.metadata(),
+            &definition.composite(),
+            descriptor,
+            ctx,
+        )?)),
+        _ => Err(ReifyError::NotACustomBlock(uuid)),
+    }
+}
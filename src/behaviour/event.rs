@@ -0,0 +1,96 @@
+//! # Events
+//!
+//! Behaviours don't run on their own: they respond to events, like the object they belong to being
+//! created, a frame passing or a key being pressed. Each event may carry some data, its _payload_,
+//! whose format is known ahead of time.
+
+use super::{BlockScopeDescriptor, RuntimeError};
+use crate::{
+    format::{BaseType, Format, VariantValue, validate::validate_value},
+    project::resource::ResourceDatabase,
+};
+use serde::{Deserialize, Serialize};
+
+/// Something that happened in the game that a behaviour can respond to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// The owner of the behaviour was just created.
+    ///
+    /// For the project's startup behaviour, this is when the game starts.
+    Created,
+    /// The owner of the behaviour is about to be destroyed.
+    Destroyed,
+    /// A frame has passed. Its payload is the number of the frame (an `Int`).
+    Tick,
+    /// An input action (like `confirm` or `move_left`) was pressed.
+    InputPressed { action: String },
+    /// A custom message was sent. Its payload is declared by the handler.
+    Message { name: String },
+}
+
+impl Event {
+    /// Returns the format of the payload of a builtin event,
+    /// or `None` for custom messages, whose payload is declared by their handler.
+    pub fn payload_format(&self) -> Option<Format> {
+        match self {
+            Event::Created | Event::Destroyed | Event::InputPressed { .. } => {
+                Some(Format::BaseType(BaseType::Void))
+            }
+            Event::Tick => Some(Format::BaseType(BaseType::Int)),
+            Event::Message { .. } => None,
+        }
+    }
+}
+
+/// Describes the blocks that run in response to an event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventHandlerDescriptor {
+    #[serde(flatten)]
+    pub event: Event,
+    /// The format of the payload of a custom message. Ignored for builtin events.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload: Option<Format>,
+    #[serde(flatten)]
+    pub blocks: BlockScopeDescriptor,
+}
+
+impl EventHandlerDescriptor {
+    /// Creates a new handler with no blocks for an event.
+    pub fn new(event: Event) -> Self {
+        EventHandlerDescriptor {
+            event,
+            payload: None,
+            blocks: BlockScopeDescriptor::new(),
+        }
+    }
+
+    /// Returns the format of the payload this handler receives.
+    pub fn payload_format(&self) -> Format {
+        self.event
+            .payload_format()
+            .or_else(|| self.payload.clone())
+            .unwrap_or(Format::BaseType(BaseType::Void))
+    }
+
+    /// Checks that a payload is fit to be handled by this handler.
+    ///
+    /// The formats of resources the payload's format refers to are read from `database`.
+    pub fn check_payload(
+        &self,
+        payload: &VariantValue,
+        database: &ResourceDatabase,
+    ) -> Result<(), RuntimeError> {
+        let expected = self.payload_format();
+        let problems = validate_value(payload, &expected, database);
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(RuntimeError::MismatchedPayload {
+                event: self.event.clone(),
+                expected,
+                problems,
+            })
+        }
+    }
+}
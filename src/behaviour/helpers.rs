@@ -1,22 +1,41 @@
 /// Defines a new block for the scripting language
+///
+/// Blocks have slots (that hold a value or another block) and, optionally,
+/// variables (that refer to a variable by name) and
+/// bodies (that hold a scope of blocks, like the inside of a loop).
+///
+/// The category and phrase tell editors where to list the block and how it reads
+/// (see [`crate::behaviour::registry`]). The signature lists the format each slot and variable accepts, and the format the block
+/// returns (see [`crate::behaviour::check`]).
 #[macro_export]
 macro_rules! block_define {
     (
         $name:ident {
             $($field:ident),* $(,)?
         },
+        $(variables: {
+            $($variable:ident),* $(,)?
+        },)?
+        $(bodies: {
+            $($body:ident),* $(,)?
+        },)?
+        category: $category:ident,
+        phrase: $phrase:expr,
         description: $description:expr,
+        signature: { $($input:ident : $input_format:expr),* $(,)? } -> $output:expr,
         evaluate: $evaluate:expr
     ) => {
         #[doc = concat!("Block `", stringify!($name),"` : ", $description)]
         pub struct $name {
-            $(pub $field: BlockSlot),*
+            $(pub $field: BlockSlot,)*
+            $($(pub $variable: VariableRef,)*)?
+            $($(pub $body: BlockScope,)*)?
         }
 
         impl TypedBlock for $name {
-            fn evaluate(&self) -> VariantValue {
+            fn evaluate(&self, ctx: &mut ExecutionContext) -> Result<VariantValue, RuntimeError> {
                 // Use the provided evaluation function
-                $evaluate(self)
+                $evaluate(self, ctx)
             }
         }
 
@@ -26,13 +45,45 @@ macro_rules! block_define {
                 $description
             }
 
+            fn signature() -> $crate::behaviour::check::BlockSignature {
+                #[allow(unused_imports)]
+                use $crate::behaviour::check::formats::*;
+                $crate::behaviour::check::BlockSignature {
+                    inputs: vec![$((stringify!($input).into(), $input_format)),*],
+                    output: $output,
+                }
+            }
+
+            fn metadata() -> $crate::behaviour::registry::BlockMetadata {
+                use $crate::behaviour::registry::{BlockCategory, BlockMetadata};
+                let signature = Self::signature();
+                BlockMetadata {
+                    category: BlockCategory::$category,
+                    phrase: $phrase.into(),
+                    description: $description.into(),
+                    contents: BlockMetadata::contents_from_signature(
+                        &signature,
+                        &[$(stringify!($field)),*],
+                        &[$($(stringify!($variable)),*)?],
+                        &[$($(stringify!($body)),*)?],
+                    ),
+                    output: signature.output,
+                }
+            }
+
             fn create() -> Self {
                 $name {
-                    $($field: BlockSlot::new()),*
+                    $($field: BlockSlot::new(),)*
+                    $($($variable: VariableRef::default(),)*)?
+                    $($($body: BlockScope::new(),)*)?
                 }
             }
 
-            fn from_descriptor(descriptor: &BlockInstanceDescriptor) -> Result<Self, ReifyError> {
+            #[allow(unused_mut, unused_variables)]
+            fn from_descriptor(
+                descriptor: &BlockInstanceDescriptor,
+                ctx: &$crate::behaviour::ReifyContext,
+            ) -> Result<Self, ReifyError<'static>> {
                 let mut block = Self::create();
                 $(
                     let field = descriptor
@@ -40,13 +91,20 @@ macro_rules! block_define {
                         .get(stringify!($field))
                         .ok_or(ReifyError::MissingField(stringify!($field)))?;
                     let slot: BlockSlot = match field {
-                        super::BlockContentDescriptor::Slot(block_slot_descriptor) => {
+                        $crate::behaviour::BlockContentDescriptor::Slot(block_slot_descriptor) => {
                             match block_slot_descriptor {
-                                BlockSlotDescriptor::VariantValue(variant_value) => BlockSlot::new_with_value(variant_value.clone()),
+                                BlockSlotDescriptor::VariantValue(variant_value) => {
+                                    $crate::behaviour::check::check_value(
+                                        &Self::signature(),
+                                        stringify!($field),
+                                        variant_value,
+                                    )?;
+                                    BlockSlot::new_with_value(variant_value.clone())
+                                }
                                 BlockSlotDescriptor::Block(child_block) => {
                                     let block = child_block
-                                        .reify()
-                                        .map_err(|e| ReifyError::Child(BlockSlotRef(stringify!($field)), Box::new(e)))?;
+                                        .reify(ctx)
+                                        .map_err(|e| ReifyError::Child(BlockSlotRef(stringify!($field).into()), Box::new(e)))?;
                                     let mut slot = BlockSlot::new();
                                     slot.try_place(Box::new(block))
                                         .map_err(ReifyError::BlockPlaceError)?;
@@ -54,9 +112,32 @@ macro_rules! block_define {
                                 }
                             }
                         }
+                        _ => return Err(ReifyError::ShouldBeASlot(BlockSlotRef(stringify!($field).into()))),
                     };
                     block.$field = slot;
                 )*
+                $($(
+                    let variable = descriptor
+                        .content
+                        .get(stringify!($variable))
+                        .ok_or(ReifyError::MissingField(stringify!($variable)))?;
+                    block.$variable = match variable {
+                        $crate::behaviour::BlockContentDescriptor::Variable(variable) => variable.clone(),
+                        _ => return Err(ReifyError::ShouldBeAVariable(BlockSlotRef(stringify!($variable).into()))),
+                    };
+                )*)?
+                $($(
+                    let part = descriptor
+                        .content
+                        .get(stringify!($body))
+                        .ok_or(ReifyError::MissingField(stringify!($body)))?;
+                    block.$body = match part {
+                        $crate::behaviour::BlockContentDescriptor::Part(part) => part
+                            .reify_body(ctx)
+                            .map_err(|e| ReifyError::Child(BlockSlotRef(stringify!($body).into()), Box::new(e)))?,
+                        _ => return Err(ReifyError::ShouldBeABody(BlockSlotRef(stringify!($body).into()))),
+                    };
+                )*)?
                 Ok(block)
             }
         }
@@ -4,64 +4,198 @@
 //! Behaviours also describes internal properties of an entity, as well as how it can interact with external entities.
 
 use crate::{
-    format::{BaseType, VariantValue},
-    plugin::BlockContributionRef,
+    format::{Format, ValueKind, VariantValue, validate::ValidationError},
+    plugin::{BlockContributionRef, PluginDatabase},
+    project::{
+        Project,
+        resource::{ResourceDatabase, ResourceLoadError},
+    },
 };
+pub use context::ExecutionContext;
 use either::Either;
+use event::{Event, EventHandlerDescriptor};
 use futures_signals::signal_vec::MutableVec;
 use serde::{Deserialize, Serialize};
-use std::{any::Any, collections::HashMap};
+use std::{any::Any, borrow::Cow, collections::HashMap};
 use std_blocks::BuiltinBlockRef;
+use thiserror::Error;
+use uuid::Uuid;
+use variables::{VariableDeclaration, VariableRef, Variables};
+pub mod check;
+pub mod composite;
+pub mod context;
+pub mod custom;
+pub mod event;
 pub mod helpers;
+pub mod registry;
 pub mod std_blocks;
+pub mod variables;
 
 /// A Recipe specifying a runtime behaviour (a script).
+///
+/// A behaviour is made of handlers, each of which runs in response to an [`Event`].
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BehaviourDescriptor {
-    #[serde(flatten)]
-    pub blocks: BlockScopeDescriptor,
+    /// The local variables of this behaviour, which are reset every time a handler runs.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub variables: Vec<VariableDeclaration>,
+    #[serde(default)]
+    pub handlers: Vec<EventHandlerDescriptor>,
+}
+
+impl Default for BehaviourDescriptor {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl BehaviourDescriptor {
     /// Returns a new, empty recipe.
     pub fn new() -> Self {
         BehaviourDescriptor {
-            blocks: BlockScopeDescriptor {
-                blocks: MutableVec::new(),
-            },
+            variables: Vec::new(),
+            handlers: Vec::new(),
         }
     }
 
     /// Creates an instance of a behaviour with its own running state and data.
-    pub fn reify(&self) -> BehaviourInstance {
-        let routine = self.blocks.blocks.lock_ref();
-        let block = routine.get(0).unwrap().reify().unwrap();
+    pub fn reify(&self, ctx: &ReifyContext) -> Result<BehaviourInstance<'_>, ReifyError<'static>> {
+        Ok(BehaviourInstance {
+            handlers: self.reify_handlers(ctx)?,
+            descriptor: Cow::Borrowed(self),
+        })
+    }
 
-        BehaviourInstance {
-            descriptor: self,
-            block,
-        }
+    /// Creates an instance of a behaviour that keeps the behaviour itself, for behaviours that
+    /// don't outlive their instance (like the one of an object's recipe).
+    pub fn into_instance(
+        self,
+        ctx: &ReifyContext,
+    ) -> Result<BehaviourInstance<'static>, ReifyError<'static>> {
+        Ok(BehaviourInstance {
+            handlers: self.reify_handlers(ctx)?,
+            descriptor: Cow::Owned(self),
+        })
+    }
+
+    /// Reifies every handler of this behaviour, in order.
+    fn reify_handlers(&self, ctx: &ReifyContext) -> Result<Vec<BlockScope>, ReifyError<'static>> {
+        self.handlers
+            .iter()
+            .enumerate()
+            .map(|(index, handler)| {
+                handler
+                    .blocks
+                    .reify(ctx)
+                    .map_err(|e| ReifyError::InHandler(index, Box::new(e)))
+            })
+            .collect()
     }
 }
 
 pub struct BehaviourInstance<'game> {
-    pub descriptor: &'game BehaviourDescriptor,
-    pub block: Box<dyn TypedBlock>,
+    pub descriptor: Cow<'game, BehaviourDescriptor>,
+    /// The reified handlers, in the same order as in the descriptor.
+    pub handlers: Vec<BlockScope>,
 }
 
-impl<'game> BehaviourInstance<'game> {
-    pub fn execute(&self) {
-        self.block.evaluate();
+impl BehaviourInstance<'_> {
+    /// Runs every handler of this behaviour that responds to an event, in order.
+    ///
+    /// Each handler gets its own fresh set of local variables, and the payload of the event.
+    pub fn dispatch(
+        &self,
+        event: &Event,
+        payload: VariantValue,
+        ctx: &mut ExecutionContext,
+    ) -> Result<(), RuntimeError> {
+        for (descriptor, handler) in self.descriptor.handlers.iter().zip(&self.handlers) {
+            if descriptor.event != *event {
+                continue;
+            }
+            descriptor.check_payload(&payload, &ctx.game.project.resource_database)?;
+            ctx.locals = Variables::from(self.descriptor.variables.as_slice());
+            ctx.payload = payload.clone();
+            handler.evaluate(ctx)?;
+            ctx.returned = None;
+        }
+        Ok(())
     }
 }
 
-/// Describes a block in a recipe while not running yet.
+/// Describes a sequence of blocks in a recipe while not running yet.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BlockScopeDescriptor {
     pub blocks: MutableVec<BlockInstanceDescriptor>,
 }
+
+impl Default for BlockScopeDescriptor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BlockScopeDescriptor {
+    /// Returns a new, empty scope.
+    pub fn new() -> Self {
+        BlockScopeDescriptor {
+            blocks: MutableVec::new(),
+        }
+    }
+
+    /// Transforms every block in this scope into real blocks, keeping their order.
+    pub fn reify(&self, ctx: &ReifyContext) -> Result<BlockScope, ReifyError<'static>> {
+        BlockScope::from_descriptors(self.blocks.lock_ref().iter(), ctx)
+    }
+}
+
+/// A sequence of blocks that are evaluated one after the other.
+///
+/// A scope is a block itself (one that returns [`VariantValue::Void`]),
+/// so it can be used as the body of other blocks. An empty scope does nothing.
+#[derive(Default)]
+pub struct BlockScope {
+    pub blocks: Vec<Box<dyn TypedBlock>>,
+}
+
+impl BlockScope {
+    /// Returns a new, empty scope.
+    pub fn new() -> Self {
+        BlockScope { blocks: Vec::new() }
+    }
+
+    /// Reifies a sequence of block descriptors into a scope.
+    pub fn from_descriptors<'a>(
+        descriptors: impl IntoIterator<Item = &'a BlockInstanceDescriptor>,
+        ctx: &ReifyContext,
+    ) -> Result<Self, ReifyError<'static>> {
+        let blocks = descriptors
+            .into_iter()
+            .enumerate()
+            .map(|(index, block)| {
+                block
+                    .reify(ctx)
+                    .map_err(|e| ReifyError::InScope(index, Box::new(e)))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(BlockScope { blocks })
+    }
+}
+
+impl TypedBlock for BlockScope {
+    fn evaluate(&self, ctx: &mut ExecutionContext) -> Result<VariantValue, RuntimeError> {
+        for block in &self.blocks {
+            block.evaluate(ctx)?;
+            if ctx.is_returning() {
+                break;
+            }
+        }
+        Ok(VariantValue::Void)
+    }
+}
 /// Describes a block in a recipe while not running yet.
-#[derive(Debug, PartialEq, Clone, Eq, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct BlockInstanceDescriptor {
     pub source: BlockSourceDescriptor,
     #[serde(flatten, serialize_with = "ordered_map")]
@@ -80,45 +214,54 @@ where
     ordered.serialize(serializer)
 }
 
-#[derive(Debug, PartialEq, Clone, Eq, Serialize, Deserialize)]
+/// A builtin block was asked for by an id that no builtin block has.
+#[derive(Debug, Error)]
+#[error("there is no builtin block called `{0}`")]
+pub struct UnknownBuiltinBlock(pub String);
+
+/// Describes something that fills a block, which can be a slot, a variable or a part with a body.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum BlockContentDescriptor {
     Slot(BlockSlotDescriptor),
+    Variable(VariableRef),
+    Part(BlockPartDescriptor),
 }
 
 impl BlockInstanceDescriptor {
+    /// Describes a builtin block (when `plugin_id` is `"builtin"`) or a block contributed by a
+    /// plugin, filled with some content.
+    ///
+    /// Fails if the block is builtin, but there is no builtin block called `block_id`.
     pub fn new(
         plugin_id: String,
         block_id: String,
         content: HashMap<String, BlockContentDescriptor>,
-    ) -> Self {
-        Self {
-            source: if plugin_id == "builtin" {
-                BlockSourceDescriptor::Builtin(
-                    block_id.parse().expect("Builtin block id not found!!!"),
-                )
-            } else {
-                BlockSourceDescriptor::Plugin(BlockContributionRef {
-                    plugin_id,
-                    block_id,
-                })
-            },
-            content,
-        }
+    ) -> Result<Self, UnknownBuiltinBlock> {
+        let source = if plugin_id == "builtin" {
+            BlockSourceDescriptor::Builtin(
+                block_id
+                    .parse()
+                    .map_err(|_| UnknownBuiltinBlock(block_id))?,
+            )
+        } else {
+            BlockSourceDescriptor::Plugin(BlockContributionRef {
+                plugin_id,
+                block_id,
+            })
+        };
+        Ok(Self { source, content })
     }
 
     /// Transforms a block descriptor into a real block that can be executed and whatnot!
-    pub fn reify(&self) -> Result<Box<dyn TypedBlock>, ReifyError> {
+    pub fn reify(&self, ctx: &ReifyContext) -> Result<Box<dyn TypedBlock>, ReifyError<'static>> {
         match &self.source {
-            BlockSourceDescriptor::Builtin(builtin_block_ref) => match builtin_block_ref {
-                BuiltinBlockRef::Int => Ok(Box::new(std_blocks::Int::from_descriptor(&self)?)),
-                BuiltinBlockRef::Add => Ok(Box::new(std_blocks::Add::from_descriptor(&self)?)),
-                BuiltinBlockRef::Log => Ok(Box::new(std_blocks::Log::from_descriptor(&self)?)),
-                BuiltinBlockRef::ChangeScreen => {
-                    Ok(Box::new(std_blocks::ChangeScreen::from_descriptor(&self)?))
-                }
-            },
-            BlockSourceDescriptor::Plugin(_) => unimplemented!(),
+            BlockSourceDescriptor::Builtin(builtin_block_ref) => builtin_block_ref.reify(self, ctx),
+            BlockSourceDescriptor::Plugin(contribution) => ctx
+                .plugins
+                .ok_or_else(|| ReifyError::PluginNotFound(contribution.plugin_id.clone()))?
+                .reify_block(contribution, self, ctx),
+            BlockSourceDescriptor::Custom(uuid) => custom::reify_custom_block(*uuid, self, ctx),
         }
     }
 }
@@ -128,26 +271,56 @@ impl BlockInstanceDescriptor {
 /// For example, block that reads `if <cond> then { block } else { block2 }` has two parts,
 /// the "if" and the "else." The phrases of the parts are the "if (cond) then" and "else",
 /// and the blocks are the "{ block }" and "{block2}".
-#[derive(Debug, PartialEq, Clone, Eq, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct BlockPartDescriptor {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub phrase: Vec<BlockSlotDescriptor>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub body: Option<Vec<BlockInstanceDescriptor>>,
 }
 
+impl BlockPartDescriptor {
+    /// Transforms the body of this part into a scope (which is empty if there is no body).
+    pub fn reify_body(&self, ctx: &ReifyContext) -> Result<BlockScope, ReifyError<'static>> {
+        match &self.body {
+            Some(body) => BlockScope::from_descriptors(body, ctx),
+            None => Ok(BlockScope::new()),
+        }
+    }
+}
+
 /// Describes what goes in a block's slot, which can be a block or a value.
-#[derive(Debug, PartialEq, Clone, Eq, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case", untagged)]
 pub enum BlockSlotDescriptor {
     Block(BlockInstanceDescriptor),
     VariantValue(VariantValue),
 }
 
+impl BlockSlotDescriptor {
+    /// Transforms what goes in a slot into a real slot.
+    pub fn reify(&self, ctx: &ReifyContext) -> Result<BlockSlot, ReifyError<'static>> {
+        match self {
+            BlockSlotDescriptor::VariantValue(value) => {
+                Ok(BlockSlot::new_with_value(value.clone()))
+            }
+            BlockSlotDescriptor::Block(block) => {
+                let mut slot = BlockSlot::new();
+                slot.try_place(Box::new(block.reify(ctx)?))
+                    .map_err(ReifyError::BlockPlaceError)?;
+                Ok(slot)
+            }
+        }
+    }
+}
+
 /// Describes which block to be created for a block descriptor.
 #[derive(Debug, PartialEq, Clone, Eq)]
 pub enum BlockSourceDescriptor {
     Plugin(BlockContributionRef),
     Builtin(BuiltinBlockRef),
+    /// A block defined by a resource of the project (see [`custom::CustomBlockDefinition`]).
+    Custom(Uuid),
 }
 
 impl Serialize for BlockSourceDescriptor {
@@ -159,14 +332,14 @@ impl Serialize for BlockSourceDescriptor {
             BlockSourceDescriptor::Plugin(BlockContributionRef {
                 plugin_id,
                 block_id,
-            }) => {
-                let plugin_id = plugin_id;
-                serializer.serialize_str(&format!("{}:{}", plugin_id, block_id))
-            }
+            }) => serializer.serialize_str(&format!("{}:{}", plugin_id, block_id)),
             BlockSourceDescriptor::Builtin(builtin) => {
                 let block_id = builtin.to_string();
                 serializer.serialize_str(&format!("builtin:{}", block_id))
             }
+            BlockSourceDescriptor::Custom(uuid) => {
+                serializer.serialize_str(&format!("custom:{}", uuid))
+            }
         }
     }
 }
@@ -183,7 +356,7 @@ impl<'de> Deserialize<'de> for BlockSourceDescriptor {
 
             fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
                 formatter.write_str(
-                    "a string in the format `<plugin_id>:<block_id>` or `builtin:<block_id>`",
+                    "a string in the format `<plugin_id>:<block_id>`, `builtin:<block_id>` or `custom:<uuid>`",
                 )
             }
 
@@ -205,6 +378,12 @@ impl<'de> Deserialize<'de> for BlockSourceDescriptor {
 
                         Ok(BlockSourceDescriptor::Builtin(builtin_block_ref))
                     }
+                    "custom" => {
+                        let uuid = parts[1]
+                            .parse::<Uuid>()
+                            .map_err(|_| E::custom("invalid custom block uuid"))?;
+                        Ok(BlockSourceDescriptor::Custom(uuid))
+                    }
                     plugin_id => {
                         let block_id = parts[1].to_string();
                         let plugin_id = plugin_id.to_string();
@@ -225,11 +404,20 @@ pub trait Block {
     /// Returns information about what this block does (and what it returns).
     fn description() -> &'static str;
 
+    /// Returns the formats this block accepts in its slots and the format it returns.
+    fn signature() -> check::BlockSignature;
+
+    /// Returns everything an editor needs to know about this block.
+    fn metadata() -> registry::BlockMetadata;
+
     /// Creates a new block with default values.
     fn create() -> Self;
 
     /// Creates a block from a [`BlockInstanceDescriptor`].
-    fn from_descriptor(descriptor: &BlockInstanceDescriptor) -> Result<Self, ReifyError>
+    fn from_descriptor(
+        descriptor: &BlockInstanceDescriptor,
+        ctx: &ReifyContext,
+    ) -> Result<Self, ReifyError<'static>>
     where
         Self: Sized;
 }
@@ -238,22 +426,189 @@ pub trait Block {
 #[derive(Debug)]
 pub enum ReifyError<'err> {
     ShouldBeAVariant(BlockSlotRef<'err>),
-    /// The type provided for whatever filled the slot was incorrect.
-    /// This error also carries the expected type.
-    MismatchedType(BlockSlotRef<'err>, BaseType),
+    /// The content was expected to be a slot, but something else was provided.
+    ShouldBeASlot(BlockSlotRef<'err>),
+    /// The content was expected to be a part with a body, but something else was provided.
+    ShouldBeABody(BlockSlotRef<'err>),
+    /// The content was expected to be a reference to a variable, but something else was provided.
+    ShouldBeAVariable(BlockSlotRef<'err>),
+    /// The value written in the slot doesn't fit the format the slot accepts.
+    /// This error also carries the expected format.
+    MismatchedType(BlockSlotRef<'err>, Format),
     BlockPlaceError(BlockPlaceError),
     Child(BlockSlotRef<'err>, Box<ReifyError<'err>>),
+    /// A block inside of a scope failed to reify. This error also carries its position in the scope.
+    InScope(usize, Box<ReifyError<'err>>),
+    /// An event handler failed to reify. This error also carries its position in the behaviour.
+    InHandler(usize, Box<ReifyError<'err>>),
     MissingField(&'static str),
+    /// A plugin block was used, but its plugin is not installed.
+    PluginNotFound(String),
+    /// A plugin block was used, but its plugin is not active in the project.
+    PluginInactive(String),
+    /// A plugin block was used, but its plugin doesn't contribute a block by that id.
+    UnknownPluginBlock(BlockContributionRef),
+    /// A custom block was used, but the resource defining it could not be loaded.
+    UnloadableCustomBlock(Uuid, ResourceLoadError),
+    /// A custom block was used, but the resource it refers to doesn't define a block.
+    NotACustomBlock(Uuid),
+}
+
+/// What blocks can look at while they're being reified.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ReifyContext<'r> {
+    /// The plugins whose blocks can be used. Without them, plugin blocks fail to reify.
+    pub plugins: Option<&'r PluginDatabase>,
+    /// The resources custom blocks are defined in. Without them, custom blocks fail to reify.
+    pub resources: Option<&'r ResourceDatabase>,
+}
+
+impl<'r> ReifyContext<'r> {
+    /// Creates a context where only builtin blocks can be used.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Lets the blocks of the active plugins of a database be used.
+    pub fn with_plugins(mut self, plugins: &'r PluginDatabase) -> Self {
+        self.plugins = Some(plugins);
+        self
+    }
+
+    /// Lets the custom blocks defined in a database be used.
+    pub fn with_resources(mut self, resources: &'r ResourceDatabase) -> Self {
+        self.resources = Some(resources);
+        self
+    }
+
+    /// Creates a context where every block available to a project can be used.
+    pub fn for_project(project: &'r Project) -> Self {
+        Self::new()
+            .with_plugins(&project.plugin_database)
+            .with_resources(&project.resource_database)
+    }
+}
+
+/// Describes an error that happened while a block was being evaluated.
+///
+/// Unlike a [`ReifyError`], which is caught before a behaviour runs, these can only be
+/// detected while it's running -- they stop the behaviour, but never the game.
+#[derive(Debug, Error)]
+pub enum RuntimeError {
+    /// A slot was filled with a value of the wrong type.
+    #[error("block `{block}` expected {expected:?} in slot `{slot}`, but found {found:?}")]
+    MismatchedType {
+        block: &'static str,
+        slot: &'static str,
+        expected: ValueKind,
+        found: ValueKind,
+    },
+    /// A slot was expected to hold a number (either an `Int` or a `Float`).
+    #[error("block `{block}` expected a number in slot `{slot}`, but found {found:?}")]
+    ExpectedNumber {
+        block: &'static str,
+        slot: &'static str,
+        found: ValueKind,
+    },
+    /// The result of an arithmetic operation does not fit in its type.
+    #[error("block `{block}` overflowed")]
+    Overflow { block: &'static str },
+    /// A number was divided by zero.
+    #[error("block `{block}` divided by zero")]
+    DivisionByZero { block: &'static str },
+    /// A list was accessed at a position where there is no item.
+    #[error("block `{block}` tried to get item {index} of a list with {length} items")]
+    IndexOutOfBounds {
+        block: &'static str,
+        index: i32,
+        length: usize,
+    },
+    /// A tuple was accessed at a field it doesn't have.
+    #[error("block `{block}` tried to get field `{field}` of a tuple that doesn't have it")]
+    UnknownField { block: &'static str, field: String },
+    /// A variable was used, but it was never declared in its scope.
+    #[error("variable `{0}` was not declared")]
+    UndeclaredVariable(String),
+    /// A variable was assigned a value that doesn't match the format it was declared with.
+    #[error(
+        "variable `{variable}` holds {expected}, but was assigned a value that doesn't fit ({})",
+        problems
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ")
+    )]
+    MismatchedVariableType {
+        variable: String,
+        expected: Format,
+        problems: Vec<ValidationError>,
+    },
+    /// An event was sent with a payload that doesn't match the format its handler expects.
+    #[error(
+        "handler for {event:?} expected a payload of {expected}, but got one that doesn't fit ({})",
+        problems
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ")
+    )]
+    MismatchedPayload {
+        event: Event,
+        expected: Format,
+        problems: Vec<ValidationError>,
+    },
+    /// An object variable was used by a behaviour that doesn't belong to any object.
+    #[error("there is no object to read object variables from")]
+    NoOwningObject,
+    /// A resource could not be loaded while the behaviour was running.
+    #[error("block `{block}` could not load resource {uuid}: {error:?}")]
+    UnloadableResource {
+        block: &'static str,
+        uuid: Uuid,
+        error: ResourceLoadError,
+    },
+    /// A resource was expected to hold custom data, but it's some other kind of resource.
+    #[error("block `{block}` expected resource {uuid} to hold custom data")]
+    NotCustomData { block: &'static str, uuid: Uuid },
+    /// A resource holds custom data, but it wasn't decoded (it's still loading).
+    #[error("block `{block}` read the data of resource {uuid} before it was decoded")]
+    UndecodedData { block: &'static str, uuid: Uuid },
+    /// Custom blocks ran inside each other too many times (probably because one uses itself forever).
+    #[error("custom blocks ran inside each other more than {0} times")]
+    TooDeep(usize),
+    /// Loops went around too many times in a single run (probably because one never ends).
+    #[error("block `{block}` went over the limit of {limit} loop iterations per run")]
+    TooManyIterations { block: &'static str, limit: usize },
+    /// A block that is only reified when it first runs failed to reify.
+    #[error("a block failed to build while running: {0:?}")]
+    Reify(Box<ReifyError<'static>>),
+}
+
+impl RuntimeError {
+    /// Creates a [`RuntimeError::MismatchedType`] for a value that was found in a slot.
+    pub fn mismatched_type(
+        block: &'static str,
+        slot: &'static str,
+        expected: impl Into<ValueKind>,
+        found: &VariantValue,
+    ) -> Self {
+        RuntimeError::MismatchedType {
+            block,
+            slot,
+            expected: expected.into(),
+            found: found.kind(),
+        }
+    }
 }
 
 pub trait TypedBlock {
-    /// Evaluates the block and produces a value.
-    fn evaluate(&self) -> VariantValue;
+    /// Evaluates the block within a context and produces a value.
+    fn evaluate(&self, ctx: &mut ExecutionContext) -> Result<VariantValue, RuntimeError>;
 }
 
 /// Describes the position a slot occupies within its block.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct BlockSlotRef<'a>(&'a str);
+pub struct BlockSlotRef<'a>(pub Cow<'a, str>);
 
 /// If a user failed to snap a block to another one,
 /// this enum carries the motive why.
@@ -268,6 +623,12 @@ pub enum BlockPlaceError {
 /// A slot for a block to be placed inside of.
 pub struct BlockSlot(pub Either<Box<dyn TypedBlock>, VariantValue>);
 
+impl Default for BlockSlot {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl BlockSlot {
     /// Creates a new slot filled with a default TDefault.
     pub fn new() -> Self {
@@ -283,9 +644,9 @@ impl BlockSlot {
     /// Fails if the slot is full or if the output type of the block
     /// does not match the type of this slot.
     ///
-    /// It's not possible to enforce this in Rust,
-    /// but `what` here should be a `Box<Box<dyn Block<Output = ...>>>`.
-    /// Failure to do that will simply return a [`BlockPlaceError::FormatMismatch`].
+    /// `what` here should be a `Box<Box<dyn TypedBlock>>`; failure to do that will simply return a
+    /// [`BlockPlaceError::FormatMismatch`]. Blocks don't know their output format once they're
+    /// reified, so formats are checked beforehand, on the descriptors, by a [`check::TypeChecker`].
     pub fn try_place(&mut self, what: Box<dyn Any>) -> Result<(), BlockPlaceError> {
         if self.0.is_left() {
             return Err(BlockPlaceError::NotAvailable(what));
@@ -310,10 +671,10 @@ impl BlockSlot {
     }
 
     /// "Just evaluates" the content of this slot.
-    pub fn just_evaluate(&self) -> VariantValue {
+    pub fn just_evaluate(&self, ctx: &mut ExecutionContext) -> Result<VariantValue, RuntimeError> {
         match &self.0 {
-            Either::Left(block) => block.evaluate(),
-            Either::Right(value) => value.clone(),
+            Either::Left(block) => block.evaluate(ctx),
+            Either::Right(value) => Ok(value.clone()),
         }
     }
 }
@@ -0,0 +1,290 @@
+//! # Block registry
+//!
+//! Everything an editor needs to know about the blocks it can offer: what they're called, how
+//! they read, what goes in them and what they return. The registry can be serialized to JSON,
+//! so that external editors can build their block palettes from it.
+
+use super::{
+    BlockSourceDescriptor,
+    check::{BlockSignature, NUMBER, UNKNOWN},
+    std_blocks::BuiltinBlockRef,
+};
+use crate::{
+    format::{Format, VariantValue},
+    plugin::{BlockContributionRef, PluginDatabase},
+    project::resource::{ResourceData, ResourceDatabase},
+};
+use serde::{Deserialize, Serialize};
+
+/// The group a block is listed under in a palette.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockCategory {
+    Values,
+    Operators,
+    Text,
+    Lists,
+    Structures,
+    Game,
+    Control,
+    Variables,
+    Other,
+}
+
+/// Describes a block for editors.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockMetadata {
+    pub category: BlockCategory,
+    /// How the block reads, with its contents in braces, like `"add {a} to {b}"`.
+    pub phrase: String,
+    /// What the block does (and what it returns).
+    pub description: String,
+    /// The slots, variables and bodies of the block, in the order they were declared.
+    pub contents: Vec<BlockContentMetadata>,
+    /// The format of the value the block returns.
+    pub output: Format,
+}
+
+/// Describes something that fills a block.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockContentMetadata {
+    pub name: String,
+    #[serde(flatten)]
+    pub kind: BlockContentKind,
+}
+
+/// What kind of thing fills a block, and what it accepts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BlockContentKind {
+    /// A slot that holds a value or another block.
+    Slot {
+        format: Format,
+        default: VariantValue,
+    },
+    /// A reference to a variable.
+    Variable { format: Format },
+    /// A scope of blocks, like the inside of a loop.
+    Body,
+}
+
+impl BlockMetadata {
+    /// Returns the formats the slots and variables of the block accept, and the format it
+    /// returns.
+    pub fn signature(&self) -> BlockSignature {
+        BlockSignature {
+            inputs: self
+                .contents
+                .iter()
+                .filter_map(|content| match &content.kind {
+                    BlockContentKind::Slot { format, .. }
+                    | BlockContentKind::Variable { format } => {
+                        Some((content.name.clone().into(), format.clone()))
+                    }
+                    BlockContentKind::Body => None,
+                })
+                .collect(),
+            output: self.output.clone(),
+        }
+    }
+
+    /// Describes the contents of a block from its signature.
+    ///
+    /// Slots are given the default value of the format they accept.
+    pub fn contents_from_signature(
+        signature: &BlockSignature,
+        slots: &[&str],
+        variables: &[&str],
+        bodies: &[&str],
+    ) -> Vec<BlockContentMetadata> {
+        let format_of = |name: &str| {
+            signature
+                .input(name)
+                .cloned()
+                .unwrap_or(Format::Parameter(UNKNOWN.into()))
+        };
+
+        let slots = slots.iter().map(|slot| {
+            let format = format_of(slot);
+            let default = match &format {
+                Format::Parameter(name) if name == NUMBER => VariantValue::Int(0),
+                format => format.default_value(),
+            };
+            (slot, BlockContentKind::Slot { format, default })
+        });
+        let variables = variables.iter().map(|variable| {
+            let format = format_of(variable);
+            (variable, BlockContentKind::Variable { format })
+        });
+        let bodies = bodies.iter().map(|body| (body, BlockContentKind::Body));
+
+        slots
+            .chain(variables)
+            .chain(bodies)
+            .map(|(name, kind)| BlockContentMetadata {
+                name: name.to_string(),
+                kind,
+            })
+            .collect()
+    }
+}
+
+/// A block that can be used, and where it comes from.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RegisteredBlock {
+    /// The source of the block, like `builtin:add` or `my_plugin:jump`.
+    pub id: BlockSourceDescriptor,
+    #[serde(flatten)]
+    pub metadata: BlockMetadata,
+}
+
+/// Every block that can be used in a project: the builtin ones and those of active plugins.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BlockRegistry {
+    pub blocks: Vec<RegisteredBlock>,
+}
+
+impl BlockRegistry {
+    /// Creates a registry with only the builtin blocks.
+    pub fn builtin() -> Self {
+        BlockRegistry {
+            blocks: BuiltinBlockRef::ALL
+                .iter()
+                .map(|builtin| RegisteredBlock {
+                    id: BlockSourceDescriptor::Builtin(builtin.clone()),
+                    metadata: builtin.metadata(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Creates a registry with the builtin blocks and the blocks of every active plugin.
+    ///
+    /// Blocks registered as Rust types (see [`PluginDatabase::register_block`]) are described by
+    /// their own metadata, and are listed after the ones in the plugin's manifest.
+    pub fn new(plugins: &PluginDatabase) -> Self {
+        let mut registry = Self::builtin();
+        for plugin in plugins.plugins.iter().filter(|plugin| plugin.is_active()) {
+            let metadata = plugin.metadata();
+            let mut registered = plugins
+                .registered_blocks()
+                .filter(|(contribution, _)| contribution.plugin_id == metadata.id())
+                .collect::<Vec<_>>();
+            // Registered blocks are stored in no particular order.
+            registered.sort_by(|(a, _), (b, _)| a.block_id.cmp(&b.block_id));
+
+            for block in &metadata.contributions().blocks {
+                let contribution = BlockContributionRef {
+                    plugin_id: metadata.id().to_string(),
+                    block_id: block.id.clone(),
+                };
+                let metadata = match registered
+                    .iter()
+                    .position(|(registered, _)| **registered == contribution)
+                {
+                    Some(index) => registered.remove(index).1,
+                    None => &block.metadata,
+                };
+                registry.blocks.push(RegisteredBlock {
+                    id: BlockSourceDescriptor::Plugin(contribution),
+                    metadata: metadata.clone(),
+                });
+            }
+            for (contribution, metadata) in registered {
+                registry.blocks.push(RegisteredBlock {
+                    id: BlockSourceDescriptor::Plugin(contribution.clone()),
+                    metadata: metadata.clone(),
+                });
+            }
+        }
+        registry
+    }
+
+    /// Adds the custom blocks defined in the resources of a project.
+    ///
+    /// This loads every resource of the database.
+    pub fn with_custom_blocks(mut self, resources: &ResourceDatabase) -> Self {
+        let mut custom_blocks = resources
+            .uuids()
+            .into_iter()
+            .filter_map(|uuid| {
+                let resource = resources.get(&uuid).ok()?;
+                let resource = resource.lock_ref();
+                match resource.data() {
+                    ResourceData::Block(definition) => Some(RegisteredBlock {
+                        id: BlockSourceDescriptor::Custom(uuid),
+                        metadata: definition.metadata(),
+                    }),
+                    _ => None,
+                }
+            })
+            .collect::<Vec<_>>();
+        // Resources are stored in no particular order.
+        custom_blocks.sort_by(|a, b| a.metadata.phrase.cmp(&b.metadata.phrase));
+        self.blocks.extend(custom_blocks);
+        self
+    }
+
+    /// Returns the block registered under a source, if there's one.
+    pub fn get(&self, id: &BlockSourceDescriptor) -> Option<&BlockMetadata> {
+        self.blocks
+            .iter()
+            .find(|block| block.id == *id)
+            .map(|block| &block.metadata)
+    }
+
+    /// Returns the blocks listed under a category, in order.
+    pub fn in_category(&self, category: BlockCategory) -> impl Iterator<Item = &RegisteredBlock> {
+        self.blocks
+            .iter()
+            .filter(move |block| block.metadata.category == category)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        behaviour::{BehaviourDescriptor, Block, check::TypeChecker, std_blocks::Add},
+        plugin::PluginRef,
+    };
+    use serde_json::json;
+
+    #[test]
+    fn registered_blocks_are_described_by_their_type() {
+        let metadata = serde_json::from_value(json!({
+            "name": "Math",
+            "id": "math",
+            "description": "",
+            "version": "0.1.0",
+            "authors": [],
+            "plugin_contributions": {}
+        }))
+        .unwrap();
+        let mut plugins = PluginDatabase::default();
+        plugins
+            .plugins
+            .push(PluginRef::new(metadata, "math".into(), true));
+        plugins.register_block::<Add>("math", "plus");
+
+        let registry = BlockRegistry::new(&plugins);
+        let plus = BlockSourceDescriptor::Plugin(BlockContributionRef {
+            plugin_id: "math".into(),
+            block_id: "plus".into(),
+        });
+        assert_eq!(registry.get(&plus), Some(&Add::metadata()));
+
+        let behaviour: BehaviourDescriptor = serde_json::from_value(json!({
+            "handlers": [{
+                "event": "tick",
+                "blocks": [{
+                    "source": "builtin:log",
+                    "what": { "source": "math:plus", "a": 1, "b": "two" }
+                }]
+            }]
+        }))
+        .unwrap();
+        let errors = TypeChecker::new().with_registry(registry).check(&behaviour);
+        assert_eq!(errors.len(), 1, "{errors:?}");
+    }
+}
@@ -0,0 +1,126 @@
+//! Blocks that decide which blocks run, and how many times.
+//!
+//! Loops (including [`ForEachItem`](super::ForEachItem)) share a budget of [`MAX_ITERATIONS`]
+//! per run of a behaviour, so that a loop that never ends stops the behaviour with an error
+//! instead of hanging the game (or the editor).
+
+use super::{count_iteration, evaluate_int, evaluate_truth};
+use crate::{
+    behaviour::{
+        Block, BlockInstanceDescriptor, BlockScope, BlockSlot, BlockSlotDescriptor, BlockSlotRef,
+        ExecutionContext, ReifyError, RuntimeError, TypedBlock, variables::VariableRef,
+    },
+    block_define,
+    format::VariantValue,
+};
+
+/// How many times loops may go around, all together, in a single run of a behaviour.
+pub const MAX_ITERATIONS: usize = 1_000_000;
+
+block_define! {
+    If { condition },
+    bodies: { then },
+    category: Control,
+    phrase: "if {condition} then {then}",
+    description: "Runs {then} only if {condition} is true.",
+    signature: { condition: truth() } -> void(),
+    evaluate: |block: &If, ctx: &mut ExecutionContext| {
+        if evaluate_truth("If", "condition", &block.condition, ctx)? {
+            block.then.evaluate(ctx)?;
+        }
+        Ok(VariantValue::Void)
+    }
+}
+
+block_define! {
+    IfElse { condition },
+    bodies: { then, otherwise },
+    category: Control,
+    phrase: "if {condition} then {then} else {otherwise}",
+    description: "Runs {then} if {condition} is true, or {otherwise} if it's not.",
+    signature: { condition: truth() } -> void(),
+    evaluate: |block: &IfElse, ctx: &mut ExecutionContext| {
+        if evaluate_truth("IfElse", "condition", &block.condition, ctx)? {
+            block.then.evaluate(ctx)?;
+        } else {
+            block.otherwise.evaluate(ctx)?;
+        }
+        Ok(VariantValue::Void)
+    }
+}
+
+block_define! {
+    Repeat { times },
+    bodies: { body },
+    category: Control,
+    phrase: "repeat {times} times {body}",
+    description: "Runs {body} a number of {times}.",
+    signature: { times: int() } -> void(),
+    evaluate: |block: &Repeat, ctx: &mut ExecutionContext| {
+        let times = evaluate_int("Repeat", "times", &block.times, ctx)?;
+        for _ in 0..times {
+            count_iteration("Repeat", ctx)?;
+            block.body.evaluate(ctx)?;
+            if ctx.is_returning() {
+                break;
+            }
+        }
+        Ok(VariantValue::Void)
+    }
+}
+
+block_define! {
+    While { condition },
+    bodies: { body },
+    category: Control,
+    phrase: "while {condition} {body}",
+    description: "Runs {body} over and over for as long as {condition} is true.",
+    signature: { condition: truth() } -> void(),
+    evaluate: |block: &While, ctx: &mut ExecutionContext| {
+        while evaluate_truth("While", "condition", &block.condition, ctx)? {
+            count_iteration("While", ctx)?;
+            block.body.evaluate(ctx)?;
+            if ctx.is_returning() {
+                break;
+            }
+        }
+        Ok(VariantValue::Void)
+    }
+}
+
+block_define! {
+    ForEach { from, to },
+    variables: { variable },
+    bodies: { body },
+    category: Control,
+    phrase: "for each {variable} from {from} to {to} {body}",
+    description: "Runs {body} once for each number counting from {from} up to {to}, storing it in {variable}.",
+    signature: { from: int(), to: int(), variable: int() } -> void(),
+    evaluate: |block: &ForEach, ctx: &mut ExecutionContext| {
+        let from = evaluate_int("ForEach", "from", &block.from, ctx)?;
+        let to = evaluate_int("ForEach", "to", &block.to, ctx)?;
+        for index in from..=to {
+            count_iteration("ForEach", ctx)?;
+            ctx.set_variable(&block.variable, VariantValue::Int(index))?;
+            block.body.evaluate(ctx)?;
+            if ctx.is_returning() {
+                break;
+            }
+        }
+        Ok(VariantValue::Void)
+    }
+}
+
+block_define! {
+    Return { value },
+    category: Control,
+    phrase: "return {value}",
+    description: r#"Stops the custom block that is running, making it return {value}.
+
+Outside of a custom block, this stops the handler that is running."#,
+    signature: { value: param("value") } -> void(),
+    evaluate: |block: &Return, ctx: &mut ExecutionContext| {
+        ctx.returned = Some(block.value.just_evaluate(ctx)?);
+        Ok(VariantValue::Void)
+    }
+}
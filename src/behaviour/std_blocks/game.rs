@@ -0,0 +1,94 @@
+//! Blocks that interact with the running game.
+
+use crate::{
+    behaviour::{
+        Block, BlockInstanceDescriptor, BlockSlot, BlockSlotDescriptor, BlockSlotRef,
+        ExecutionContext, ReifyError, RuntimeError, TypedBlock,
+    },
+    block_define,
+    format::{ValueKind, VariantValue},
+    project::resource::ResourceData,
+};
+
+block_define! {
+    Log { what },
+    category: Game,
+    phrase: "log {what}",
+    description: "Logs a value to the standard output.",
+    signature: { what: param("what") } -> void(),
+    evaluate: |block: &Log, ctx: &mut ExecutionContext| {
+        println!("LOG {:?}", block.what.just_evaluate(ctx)?);
+        Ok(VariantValue::Void)
+    }
+}
+
+block_define! {
+    ChangeScreen { },
+    category: Game,
+    phrase: "change screen",
+    description: r#"Changes the current screen.
+
+Projects can't make screens yet, so for now this block does nothing."#,
+    signature: { } -> void(),
+    // A no-op until screens are resources that a slot of this block can refer to.
+    evaluate: |_: &ChangeScreen, _: &mut ExecutionContext| {
+        Ok(VariantValue::Void)
+    }
+}
+
+block_define! {
+    EventPayload { },
+    category: Game,
+    phrase: "event payload",
+    description: "Returns the data that came along with the event being handled.",
+    signature: { } -> param(crate::behaviour::check::PAYLOAD),
+    evaluate: |_: &EventPayload, ctx: &mut ExecutionContext| {
+        Ok(ctx.payload.clone())
+    }
+}
+
+block_define! {
+    ReadResource { resource },
+    category: Game,
+    phrase: "data of {resource}",
+    description: "Returns the data held by the custom resource {resource}.",
+    signature: { resource: param("resource") } -> unknown(),
+    evaluate: |block: &ReadResource, ctx: &mut ExecutionContext| {
+        let resource = match block.resource.just_evaluate(ctx)? {
+            VariantValue::Resource { resource } => resource,
+            other => {
+                return Err(RuntimeError::mismatched_type(
+                    "ReadResource",
+                    "resource",
+                    ValueKind::Resource,
+                    &other,
+                ));
+            }
+        };
+
+        let handle = match resource.handle {
+            Some(handle) => handle,
+            None => ctx
+                .game
+                .project
+                .resource_database
+                .get(&resource.uuid)
+                .map_err(|error| RuntimeError::UnloadableResource {
+                    block: "ReadResource",
+                    uuid: resource.uuid,
+                    error,
+                })?,
+        };
+
+        match handle.lock_ref().data() {
+            ResourceData::Custom(custom) => custom.value().cloned().ok_or(RuntimeError::UndecodedData {
+                block: "ReadResource",
+                uuid: resource.uuid,
+            }),
+            _ => Err(RuntimeError::NotCustomData {
+                block: "ReadResource",
+                uuid: resource.uuid,
+            }),
+        }
+    }
+}
@@ -1,9 +1,20 @@
 //! Collection of builtin blocks everyone can to make scripts.
 pub use super::TypedBlock;
 use super::{
-    Block, BlockInstanceDescriptor, BlockSlot, BlockSlotDescriptor, BlockSlotRef, ReifyError,
+    BlockInstanceDescriptor, BlockSlot, ExecutionContext, ReifyContext, ReifyError, RuntimeError,
 };
-use crate::{block_define, format::VariantValue};
+use crate::format::{BaseType, ValueKind, VariantValue};
+pub use control::*;
+pub use game::*;
+use ordermap::OrderMap;
+pub use structures::*;
+pub use values::*;
+pub use variables::*;
+pub mod control;
+pub mod game;
+pub mod structures;
+pub mod values;
+pub mod variables;
 
 macro_rules! StdBlocks {
     ($($block_name:ident),*) => {
@@ -16,53 +27,225 @@ macro_rules! StdBlocks {
                 $block_name
             ),*
         }
+
+        impl BuiltinBlockRef {
+            /// Every builtin block, in the order they are listed in palettes.
+            pub const ALL: &[BuiltinBlockRef] = &[$(BuiltinBlockRef::$block_name),*];
+
+            /// Creates the builtin block this refers to from a [`BlockInstanceDescriptor`].
+            pub fn reify(
+                &self,
+                descriptor: &BlockInstanceDescriptor,
+                ctx: &ReifyContext,
+            ) -> Result<Box<dyn TypedBlock>, ReifyError<'static>> {
+                use super::Block as _;
+                match self {
+                    $(
+                        BuiltinBlockRef::$block_name => {
+                            Ok(Box::new($block_name::from_descriptor(descriptor, ctx)?))
+                        }
+                    ),*
+                }
+            }
+
+            /// Returns the metadata of the builtin block this refers to.
+            pub fn metadata(&self) -> super::registry::BlockMetadata {
+                use super::Block as _;
+                match self {
+                    $(BuiltinBlockRef::$block_name => $block_name::metadata()),*
+                }
+            }
+
+            /// Returns the signature of the builtin block this refers to.
+            pub fn signature(&self) -> super::check::BlockSignature {
+                use super::Block as _;
+                match self {
+                    $(BuiltinBlockRef::$block_name => $block_name::signature()),*
+                }
+            }
+        }
     };
 }
 
 StdBlocks! {
-    Int, Add, Log, ChangeScreen
+    Int, Float, Text, Truth,
+    Add, Subtract, Multiply, Divide,
+    Equals, LessThan, GreaterThan, And, Or, Not,
+    Join, TextLength,
+    EmptyList, ListWith, ListItem, ListLength, ForEachItem,
+    EmptyTuple, TupleWith, GetField, MakeCase, IfCase,
+    Nothing, Something, IfSomething,
+    Log, ChangeScreen, EventPayload, ReadResource,
+    If, IfElse, Repeat, While, ForEach, Return,
+    SetVariable, GetVariable, ChangeVariableBy
+}
+
+/// Spends one loop iteration of the run's budget.
+fn count_iteration(block: &'static str, ctx: &mut ExecutionContext) -> Result<(), RuntimeError> {
+    if ctx.iterations >= MAX_ITERATIONS {
+        return Err(RuntimeError::TooManyIterations {
+            block,
+            limit: MAX_ITERATIONS,
+        });
+    }
+    ctx.iterations += 1;
+    Ok(())
 }
 
-block_define! {
-    Int { v },
-    description: "Returns an integer.",
-    evaluate: |block: &Int| {
-        block.v.just_evaluate()
+/// Evaluates a slot that should contain a [`VariantValue::Truth`].
+fn evaluate_truth(
+    block: &'static str,
+    slot_name: &'static str,
+    slot: &BlockSlot,
+    ctx: &mut ExecutionContext,
+) -> Result<bool, RuntimeError> {
+    match slot.just_evaluate(ctx)? {
+        VariantValue::Truth(truth) => Ok(truth),
+        other => Err(RuntimeError::mismatched_type(
+            block,
+            slot_name,
+            BaseType::Truth,
+            &other,
+        )),
     }
 }
 
-block_define! {
-    Add { a, b },
-    description: "Adds two numbers and returns them.",
-    evaluate: |block: &Add| {
-        let a = block.a.just_evaluate();
-        let b = block.b.just_evaluate();
+/// Evaluates a slot that should contain a [`VariantValue::Int`].
+fn evaluate_int(
+    block: &'static str,
+    slot_name: &'static str,
+    slot: &BlockSlot,
+    ctx: &mut ExecutionContext,
+) -> Result<i32, RuntimeError> {
+    match slot.just_evaluate(ctx)? {
+        VariantValue::Int(int) => Ok(int),
+        other => Err(RuntimeError::mismatched_type(
+            block,
+            slot_name,
+            BaseType::Int,
+            &other,
+        )),
+    }
+}
 
-        match (a, b) {
-            (VariantValue::Int(a), VariantValue::Int(b)) => {
-                VariantValue::Int(a + b)
-            },
-            _ => panic!("Type Error!")
-        }
+/// Evaluates a slot that should contain a [`VariantValue::Float`]
+/// (or an [`VariantValue::Int`], which is converted to a float).
+fn evaluate_float(
+    block: &'static str,
+    slot_name: &'static str,
+    slot: &BlockSlot,
+    ctx: &mut ExecutionContext,
+) -> Result<f32, RuntimeError> {
+    match slot.just_evaluate(ctx)? {
+        VariantValue::Float(float) => Ok(float),
+        VariantValue::Int(int) => Ok(int as f32),
+        other => Err(RuntimeError::mismatched_type(
+            block,
+            slot_name,
+            BaseType::Float,
+            &other,
+        )),
     }
 }
 
-block_define! {
-    Log { what },
-    description: "Logs a value to the standard output.",
-    evaluate: |block: &Log| {
-        println!("LOG {:?}", block.what.just_evaluate());
-        VariantValue::Void
+/// Evaluates a slot that should contain a [`VariantValue::Text`].
+fn evaluate_text(
+    block: &'static str,
+    slot_name: &'static str,
+    slot: &BlockSlot,
+    ctx: &mut ExecutionContext,
+) -> Result<String, RuntimeError> {
+    match slot.just_evaluate(ctx)? {
+        VariantValue::Text(text) => Ok(text),
+        other => Err(RuntimeError::mismatched_type(
+            block,
+            slot_name,
+            BaseType::Text,
+            &other,
+        )),
     }
 }
 
-block_define! {
-    ChangeScreen { },
-    description: r#"Changes the current screen to {new_screen}.
+/// Evaluates a slot that should contain a [`VariantValue::List`].
+fn evaluate_list(
+    block: &'static str,
+    slot_name: &'static str,
+    slot: &BlockSlot,
+    ctx: &mut ExecutionContext,
+) -> Result<Vec<VariantValue>, RuntimeError> {
+    match slot.just_evaluate(ctx)? {
+        VariantValue::List(items) => Ok(items),
+        other => Err(RuntimeError::mismatched_type(
+            block,
+            slot_name,
+            ValueKind::List,
+            &other,
+        )),
+    }
+}
+
+/// Evaluates a slot that should contain a [`VariantValue::Tuple`].
+fn evaluate_tuple(
+    block: &'static str,
+    slot_name: &'static str,
+    slot: &BlockSlot,
+    ctx: &mut ExecutionContext,
+) -> Result<OrderMap<String, VariantValue>, RuntimeError> {
+    match slot.just_evaluate(ctx)? {
+        VariantValue::Tuple { fields } => Ok(fields),
+        other => Err(RuntimeError::mismatched_type(
+            block,
+            slot_name,
+            ValueKind::Tuple,
+            &other,
+        )),
+    }
+}
+
+/// Two numbers brought to the same type so they can operate with each other.
+///
+/// If any of the numbers is a float, both are treated as floats.
+enum Numbers {
+    Int(i32, i32),
+    Float(f32, f32),
+}
+
+impl Numbers {
+    /// Brings two values to the same number type, failing if any of them is not a number.
+    fn from_values(
+        block: &'static str,
+        (a_name, a): (&'static str, VariantValue),
+        (b_name, b): (&'static str, VariantValue),
+    ) -> Result<Self, RuntimeError> {
+        match (a, b) {
+            (VariantValue::Int(a), VariantValue::Int(b)) => Ok(Numbers::Int(a, b)),
+            (VariantValue::Int(a), VariantValue::Float(b)) => Ok(Numbers::Float(a as f32, b)),
+            (VariantValue::Float(a), VariantValue::Int(b)) => Ok(Numbers::Float(a, b as f32)),
+            (VariantValue::Float(a), VariantValue::Float(b)) => Ok(Numbers::Float(a, b)),
+            (VariantValue::Int(_) | VariantValue::Float(_), other) => {
+                Err(RuntimeError::ExpectedNumber {
+                    block,
+                    slot: b_name,
+                    found: other.kind(),
+                })
+            }
+            (other, _) => Err(RuntimeError::ExpectedNumber {
+                block,
+                slot: a_name,
+                found: other.kind(),
+            }),
+        }
+    }
 
-This also acts as an implicit return that skips anything that happens on this frame until the screen changes."#,
-    evaluate: |_: &ChangeScreen| {
-        println!("Screen was changed???");
-        VariantValue::Void
+    /// Evaluates the slots `a` and `b` of a block as numbers of the same type.
+    fn evaluate(
+        block: &'static str,
+        a: &BlockSlot,
+        b: &BlockSlot,
+        ctx: &mut ExecutionContext,
+    ) -> Result<Self, RuntimeError> {
+        let a = a.just_evaluate(ctx)?;
+        let b = b.just_evaluate(ctx)?;
+        Self::from_values(block, ("a", a), ("b", b))
     }
 }
@@ -0,0 +1,224 @@
+//! Blocks that build and take apart structured values: lists, tuples, cases and maybes.
+
+use super::{count_iteration, evaluate_int, evaluate_list, evaluate_text, evaluate_tuple};
+use crate::{
+    behaviour::{
+        Block, BlockInstanceDescriptor, BlockScope, BlockSlot, BlockSlotDescriptor, BlockSlotRef,
+        ExecutionContext, ReifyError, RuntimeError, TypedBlock, variables::VariableRef,
+    },
+    block_define,
+    format::{ValueKind, VariantValue},
+};
+use ordermap::OrderMap;
+
+block_define! {
+    EmptyList { },
+    category: Lists,
+    phrase: "empty list",
+    description: "Returns a list with no items.",
+    signature: { } -> list(param("T")),
+    evaluate: |_: &EmptyList, _: &mut ExecutionContext| {
+        Ok(VariantValue::List(Vec::new()))
+    }
+}
+
+block_define! {
+    ListWith { list, item },
+    category: Lists,
+    phrase: "{list} with {item}",
+    description: "Returns {list} with {item} added to its end.",
+    signature: { list: list(param("T")), item: param("T") } -> list(param("T")),
+    evaluate: |block: &ListWith, ctx: &mut ExecutionContext| {
+        let mut list = evaluate_list("ListWith", "list", &block.list, ctx)?;
+        list.push(block.item.just_evaluate(ctx)?);
+        Ok(VariantValue::List(list))
+    }
+}
+
+block_define! {
+    ListItem { list, index },
+    category: Lists,
+    phrase: "item {index} of {list}",
+    description: "Returns the item at position {index} of {list}. The first item is at position 1.",
+    signature: { list: list(param("T")), index: int() } -> param("T"),
+    evaluate: |block: &ListItem, ctx: &mut ExecutionContext| {
+        let list = evaluate_list("ListItem", "list", &block.list, ctx)?;
+        let index = evaluate_int("ListItem", "index", &block.index, ctx)?;
+        let length = list.len();
+        usize::try_from(index)
+            .ok()
+            .and_then(|index| index.checked_sub(1))
+            .and_then(|index| list.into_iter().nth(index))
+            .ok_or(RuntimeError::IndexOutOfBounds {
+                block: "ListItem",
+                index,
+                length,
+            })
+    }
+}
+
+block_define! {
+    ListLength { list },
+    category: Lists,
+    phrase: "number of items in {list}",
+    description: "Returns the number of items in {list}.",
+    signature: { list: list(param("T")) } -> int(),
+    evaluate: |block: &ListLength, ctx: &mut ExecutionContext| {
+        let list = evaluate_list("ListLength", "list", &block.list, ctx)?;
+        i32::try_from(list.len())
+            .map(VariantValue::Int)
+            .map_err(|_| RuntimeError::Overflow { block: "ListLength" })
+    }
+}
+
+block_define! {
+    ForEachItem { list },
+    variables: { variable },
+    bodies: { body },
+    category: Lists,
+    phrase: "for each {variable} in {list} {body}",
+    description: "Runs {body} once for each item of {list}, storing the item in {variable}.",
+    signature: { list: list(param("T")), variable: param("T") } -> void(),
+    evaluate: |block: &ForEachItem, ctx: &mut ExecutionContext| {
+        for item in evaluate_list("ForEachItem", "list", &block.list, ctx)? {
+            count_iteration("ForEachItem", ctx)?;
+            ctx.set_variable(&block.variable, item)?;
+            block.body.evaluate(ctx)?;
+            if ctx.is_returning() {
+                break;
+            }
+        }
+        Ok(VariantValue::Void)
+    }
+}
+
+block_define! {
+    EmptyTuple { },
+    category: Structures,
+    phrase: "empty tuple",
+    description: "Returns a tuple with no fields.",
+    signature: { } -> unknown(),
+    evaluate: |_: &EmptyTuple, _: &mut ExecutionContext| {
+        Ok(VariantValue::Tuple { fields: OrderMap::new() })
+    }
+}
+
+block_define! {
+    TupleWith { tuple, field, value },
+    category: Structures,
+    phrase: "{tuple} with {field} set to {value}",
+    description: "Returns {tuple} with its {field} set to {value}.",
+    signature: { tuple: param("tuple"), field: text(), value: param("value") } -> unknown(),
+    evaluate: |block: &TupleWith, ctx: &mut ExecutionContext| {
+        let mut fields = evaluate_tuple("TupleWith", "tuple", &block.tuple, ctx)?;
+        let field = evaluate_text("TupleWith", "field", &block.field, ctx)?;
+        fields.insert(field, block.value.just_evaluate(ctx)?);
+        Ok(VariantValue::Tuple { fields })
+    }
+}
+
+block_define! {
+    GetField { tuple, field },
+    category: Structures,
+    phrase: "{field} of {tuple}",
+    description: "Returns the value of the {field} of {tuple}.",
+    signature: { tuple: param("tuple"), field: text() } -> unknown(),
+    evaluate: |block: &GetField, ctx: &mut ExecutionContext| {
+        let mut fields = evaluate_tuple("GetField", "tuple", &block.tuple, ctx)?;
+        let field = evaluate_text("GetField", "field", &block.field, ctx)?;
+        fields
+            .remove(&field)
+            .ok_or(RuntimeError::UnknownField { block: "GetField", field })
+    }
+}
+
+block_define! {
+    MakeCase { case, value },
+    category: Structures,
+    phrase: "case {case} with {value}",
+    description: "Returns the case named {case}, carrying {value} as its data.",
+    signature: { case: text(), value: param("value") } -> unknown(),
+    evaluate: |block: &MakeCase, ctx: &mut ExecutionContext| {
+        let case = evaluate_text("MakeCase", "case", &block.case, ctx)?;
+        let value = Box::new(block.value.just_evaluate(ctx)?);
+        Ok(VariantValue::Case { case, value })
+    }
+}
+
+block_define! {
+    IfCase { value, case },
+    variables: { data },
+    bodies: { then, otherwise },
+    category: Structures,
+    phrase: "if {value} is {case} with {data} then {then} else {otherwise}",
+    description: r#"Runs {then} if {value} is the case named {case}, or {otherwise} if it's not.
+
+When it is, the data of the case is stored in {data}."#,
+    signature: { value: param("value"), case: text(), data: param("data") } -> void(),
+    evaluate: |block: &IfCase, ctx: &mut ExecutionContext| {
+        let expected = evaluate_text("IfCase", "case", &block.case, ctx)?;
+        match block.value.just_evaluate(ctx)? {
+            VariantValue::Case { case, value } if case == expected => {
+                ctx.set_variable(&block.data, *value)?;
+                block.then.evaluate(ctx)?;
+            }
+            VariantValue::Case { .. } => {
+                block.otherwise.evaluate(ctx)?;
+            }
+            other => {
+                return Err(RuntimeError::mismatched_type("IfCase", "value", ValueKind::Case, &other))
+            }
+        }
+        Ok(VariantValue::Void)
+    }
+}
+
+block_define! {
+    Nothing { },
+    category: Structures,
+    phrase: "nothing",
+    description: "Returns a maybe that holds nothing.",
+    signature: { } -> maybe(param("T")),
+    evaluate: |_: &Nothing, _: &mut ExecutionContext| {
+        Ok(VariantValue::Maybe { maybe: None })
+    }
+}
+
+block_define! {
+    Something { value },
+    category: Structures,
+    phrase: "something holding {value}",
+    description: "Returns a maybe that holds {value}.",
+    signature: { value: param("T") } -> maybe(param("T")),
+    evaluate: |block: &Something, ctx: &mut ExecutionContext| {
+        let value = Box::new(block.value.just_evaluate(ctx)?);
+        Ok(VariantValue::Maybe { maybe: Some(value) })
+    }
+}
+
+block_define! {
+    IfSomething { maybe },
+    variables: { variable },
+    bodies: { then, otherwise },
+    category: Structures,
+    phrase: "if {maybe} holds {variable} then {then} else {otherwise}",
+    description: r#"Runs {then} if {maybe} holds something, or {otherwise} if it holds nothing.
+
+When it holds something, it is stored in {variable}."#,
+    signature: { maybe: maybe(param("T")), variable: param("T") } -> void(),
+    evaluate: |block: &IfSomething, ctx: &mut ExecutionContext| {
+        match block.maybe.just_evaluate(ctx)? {
+            VariantValue::Maybe { maybe: Some(value) } => {
+                ctx.set_variable(&block.variable, *value)?;
+                block.then.evaluate(ctx)?;
+            }
+            VariantValue::Maybe { maybe: None } => {
+                block.otherwise.evaluate(ctx)?;
+            }
+            other => {
+                return Err(RuntimeError::mismatched_type("IfSomething", "maybe", ValueKind::Maybe, &other))
+            }
+        }
+        Ok(VariantValue::Void)
+    }
+}
@@ -0,0 +1,239 @@
+//! Blocks that create and operate on values: numbers, truths and text.
+
+use super::{Numbers, evaluate_float, evaluate_int, evaluate_text, evaluate_truth};
+use crate::{
+    behaviour::{
+        Block, BlockInstanceDescriptor, BlockSlot, BlockSlotDescriptor, BlockSlotRef,
+        ExecutionContext, ReifyError, RuntimeError, TypedBlock,
+    },
+    block_define,
+    format::VariantValue,
+};
+
+block_define! {
+    Int { v },
+    category: Values,
+    phrase: "{v}",
+    description: "Returns an integer.",
+    signature: { v: int() } -> int(),
+    evaluate: |block: &Int, ctx: &mut ExecutionContext| {
+        evaluate_int("Int", "v", &block.v, ctx).map(VariantValue::Int)
+    }
+}
+
+block_define! {
+    Float { v },
+    category: Values,
+    phrase: "{v}",
+    description: "Returns a number that might have a fractional part.",
+    signature: { v: float() } -> float(),
+    evaluate: |block: &Float, ctx: &mut ExecutionContext| {
+        evaluate_float("Float", "v", &block.v, ctx).map(VariantValue::Float)
+    }
+}
+
+block_define! {
+    Text { v },
+    category: Values,
+    phrase: "{v}",
+    description: "Returns some text.",
+    signature: { v: text() } -> text(),
+    evaluate: |block: &Text, ctx: &mut ExecutionContext| {
+        evaluate_text("Text", "v", &block.v, ctx).map(VariantValue::Text)
+    }
+}
+
+block_define! {
+    Truth { v },
+    category: Values,
+    phrase: "{v}",
+    description: "Returns either true or false.",
+    signature: { v: truth() } -> truth(),
+    evaluate: |block: &Truth, ctx: &mut ExecutionContext| {
+        evaluate_truth("Truth", "v", &block.v, ctx).map(VariantValue::Truth)
+    }
+}
+
+block_define! {
+    Add { a, b },
+    category: Operators,
+    phrase: "{a} + {b}",
+    description: "Adds two numbers and returns them.",
+    signature: { a: number(), b: number() } -> number(),
+    evaluate: |block: &Add, ctx: &mut ExecutionContext| {
+        match Numbers::evaluate("Add", &block.a, &block.b, ctx)? {
+            Numbers::Int(a, b) => a
+                .checked_add(b)
+                .map(VariantValue::Int)
+                .ok_or(RuntimeError::Overflow { block: "Add" }),
+            Numbers::Float(a, b) => Ok(VariantValue::Float(a + b)),
+        }
+    }
+}
+
+block_define! {
+    Subtract { a, b },
+    category: Operators,
+    phrase: "{a} - {b}",
+    description: "Subtracts {b} from {a} and returns the result.",
+    signature: { a: number(), b: number() } -> number(),
+    evaluate: |block: &Subtract, ctx: &mut ExecutionContext| {
+        match Numbers::evaluate("Subtract", &block.a, &block.b, ctx)? {
+            Numbers::Int(a, b) => a
+                .checked_sub(b)
+                .map(VariantValue::Int)
+                .ok_or(RuntimeError::Overflow { block: "Subtract" }),
+            Numbers::Float(a, b) => Ok(VariantValue::Float(a - b)),
+        }
+    }
+}
+
+block_define! {
+    Multiply { a, b },
+    category: Operators,
+    phrase: "{a} × {b}",
+    description: "Multiplies two numbers and returns the result.",
+    signature: { a: number(), b: number() } -> number(),
+    evaluate: |block: &Multiply, ctx: &mut ExecutionContext| {
+        match Numbers::evaluate("Multiply", &block.a, &block.b, ctx)? {
+            Numbers::Int(a, b) => a
+                .checked_mul(b)
+                .map(VariantValue::Int)
+                .ok_or(RuntimeError::Overflow { block: "Multiply" }),
+            Numbers::Float(a, b) => Ok(VariantValue::Float(a * b)),
+        }
+    }
+}
+
+block_define! {
+    Divide { a, b },
+    category: Operators,
+    phrase: "{a} ÷ {b}",
+    description: r#"Divides {a} by {b} and returns the result.
+
+Dividing two integers returns an integer, discarding the remainder."#,
+    signature: { a: number(), b: number() } -> number(),
+    evaluate: |block: &Divide, ctx: &mut ExecutionContext| {
+        match Numbers::evaluate("Divide", &block.a, &block.b, ctx)? {
+            Numbers::Int(_, 0) => Err(RuntimeError::DivisionByZero { block: "Divide" }),
+            Numbers::Int(a, b) => a
+                .checked_div(b)
+                .map(VariantValue::Int)
+                .ok_or(RuntimeError::Overflow { block: "Divide" }),
+            Numbers::Float(_, 0.0) => Err(RuntimeError::DivisionByZero { block: "Divide" }),
+            Numbers::Float(a, b) => Ok(VariantValue::Float(a / b)),
+        }
+    }
+}
+
+block_define! {
+    Equals { a, b },
+    category: Operators,
+    phrase: "{a} = {b}",
+    description: "Returns whether {a} and {b} are the same value. Integers and floats are compared by their number.",
+    signature: { a: param("a"), b: param("b") } -> truth(),
+    evaluate: |block: &Equals, ctx: &mut ExecutionContext| {
+        let a = block.a.just_evaluate(ctx)?;
+        let b = block.b.just_evaluate(ctx)?;
+        let equals = match Numbers::from_values("Equals", ("a", a.clone()), ("b", b.clone())) {
+            Ok(Numbers::Int(a, b)) => a == b,
+            Ok(Numbers::Float(a, b)) => a == b,
+            Err(_) => a == b,
+        };
+        Ok(VariantValue::Truth(equals))
+    }
+}
+
+block_define! {
+    LessThan { a, b },
+    category: Operators,
+    phrase: "{a} < {b}",
+    description: "Returns whether the number {a} is less than {b}.",
+    signature: { a: number(), b: number() } -> truth(),
+    evaluate: |block: &LessThan, ctx: &mut ExecutionContext| {
+        let less = match Numbers::evaluate("LessThan", &block.a, &block.b, ctx)? {
+            Numbers::Int(a, b) => a < b,
+            Numbers::Float(a, b) => a < b,
+        };
+        Ok(VariantValue::Truth(less))
+    }
+}
+
+block_define! {
+    GreaterThan { a, b },
+    category: Operators,
+    phrase: "{a} > {b}",
+    description: "Returns whether the number {a} is greater than {b}.",
+    signature: { a: number(), b: number() } -> truth(),
+    evaluate: |block: &GreaterThan, ctx: &mut ExecutionContext| {
+        let greater = match Numbers::evaluate("GreaterThan", &block.a, &block.b, ctx)? {
+            Numbers::Int(a, b) => a > b,
+            Numbers::Float(a, b) => a > b,
+        };
+        Ok(VariantValue::Truth(greater))
+    }
+}
+
+block_define! {
+    And { a, b },
+    category: Operators,
+    phrase: "{a} and {b}",
+    description: "Returns whether both {a} and {b} are true. {b} is only evaluated if {a} is true.",
+    signature: { a: truth(), b: truth() } -> truth(),
+    evaluate: |block: &And, ctx: &mut ExecutionContext| {
+        let and = evaluate_truth("And", "a", &block.a, ctx)?
+            && evaluate_truth("And", "b", &block.b, ctx)?;
+        Ok(VariantValue::Truth(and))
+    }
+}
+
+block_define! {
+    Or { a, b },
+    category: Operators,
+    phrase: "{a} or {b}",
+    description: "Returns whether {a} or {b} is true. {b} is only evaluated if {a} is false.",
+    signature: { a: truth(), b: truth() } -> truth(),
+    evaluate: |block: &Or, ctx: &mut ExecutionContext| {
+        let or = evaluate_truth("Or", "a", &block.a, ctx)?
+            || evaluate_truth("Or", "b", &block.b, ctx)?;
+        Ok(VariantValue::Truth(or))
+    }
+}
+
+block_define! {
+    Not { v },
+    category: Operators,
+    phrase: "not {v}",
+    description: "Returns true if {v} is false, and false if it's true.",
+    signature: { v: truth() } -> truth(),
+    evaluate: |block: &Not, ctx: &mut ExecutionContext| {
+        evaluate_truth("Not", "v", &block.v, ctx).map(|v| VariantValue::Truth(!v))
+    }
+}
+
+block_define! {
+    Join { a, b },
+    category: Text,
+    phrase: "join {a} and {b}",
+    description: "Joins {a} and {b} together into a single text. Values that aren't text are converted to text.",
+    signature: { a: param("a"), b: param("b") } -> text(),
+    evaluate: |block: &Join, ctx: &mut ExecutionContext| {
+        let a = block.a.just_evaluate(ctx)?;
+        let b = block.b.just_evaluate(ctx)?;
+        Ok(VariantValue::Text(format!("{}{}", a, b)))
+    }
+}
+
+block_define! {
+    TextLength { text },
+    category: Text,
+    phrase: "length of {text}",
+    description: "Returns the number of characters in {text}.",
+    signature: { text: text() } -> int(),
+    evaluate: |block: &TextLength, ctx: &mut ExecutionContext| {
+        let text = evaluate_text("TextLength", "text", &block.text, ctx)?;
+        i32::try_from(text.chars().count())
+            .map(VariantValue::Int)
+            .map_err(|_| RuntimeError::Overflow { block: "TextLength" })
+    }
+}
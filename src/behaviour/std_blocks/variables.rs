@@ -0,0 +1,59 @@
+//! Blocks that read and write variables.
+
+use super::Numbers;
+use crate::{
+    behaviour::{
+        Block, BlockInstanceDescriptor, BlockSlot, BlockSlotDescriptor, BlockSlotRef,
+        ExecutionContext, ReifyError, RuntimeError, TypedBlock, variables::VariableRef,
+    },
+    block_define,
+    format::VariantValue,
+};
+
+block_define! {
+    SetVariable { value },
+    variables: { variable },
+    category: Variables,
+    phrase: "set {variable} to {value}",
+    description: "Sets {variable} to {value}.",
+    signature: { variable: param("T"), value: param("T") } -> void(),
+    evaluate: |block: &SetVariable, ctx: &mut ExecutionContext| {
+        let value = block.value.just_evaluate(ctx)?;
+        ctx.set_variable(&block.variable, value)?;
+        Ok(VariantValue::Void)
+    }
+}
+
+block_define! {
+    GetVariable { },
+    variables: { variable },
+    category: Variables,
+    phrase: "{variable}",
+    description: "Returns the current value of {variable}.",
+    signature: { variable: param("T") } -> param("T"),
+    evaluate: |block: &GetVariable, ctx: &mut ExecutionContext| {
+        ctx.get_variable(&block.variable)
+    }
+}
+
+block_define! {
+    ChangeVariableBy { by },
+    variables: { variable },
+    category: Variables,
+    phrase: "change {variable} by {by}",
+    description: "Changes the number in {variable} by {by}.",
+    signature: { variable: number(), by: number() } -> void(),
+    evaluate: |block: &ChangeVariableBy, ctx: &mut ExecutionContext| {
+        let current = ctx.get_variable(&block.variable)?;
+        let by = block.by.just_evaluate(ctx)?;
+        let value = match Numbers::from_values("ChangeVariableBy", ("variable", current), ("by", by))? {
+            Numbers::Int(current, by) => current
+                .checked_add(by)
+                .map(VariantValue::Int)
+                .ok_or(RuntimeError::Overflow { block: "ChangeVariableBy" })?,
+            Numbers::Float(current, by) => VariantValue::Float(current + by),
+        };
+        ctx.set_variable(&block.variable, value)?;
+        Ok(VariantValue::Void)
+    }
+}
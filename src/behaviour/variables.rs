@@ -0,0 +1,108 @@
+//! # Variables
+//!
+//! Variables store values between block evaluations. Every variable is declared with a [`Format`],
+//! and lives in one of three [`VariableScope`]s:
+//! - `global`: the story state, shared by the whole game, declared by the project's story definition;
+//! - `object`: state that belongs to a single object, declared by its recipe;
+//! - `local`: state that only lives for a single run of a behaviour, declared by the behaviour.
+
+use super::RuntimeError;
+use crate::{
+    format::{Format, VariantValue, validate::conform_value},
+    project::resource::ResourceDatabase,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// The place where a variable lives.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VariableScope {
+    /// The variable is part of the story state, and is shared by the whole game.
+    Global,
+    /// The variable belongs to the object that owns the running behaviour.
+    Object,
+    /// The variable only lives for a single run of a behaviour.
+    #[default]
+    Local,
+}
+
+/// A reference to a variable, as used by blocks.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct VariableRef {
+    pub variable: String,
+    pub scope: VariableScope,
+}
+
+/// Declares a variable and the format of the values it can hold.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VariableDeclaration {
+    pub name: String,
+    pub format: Format,
+}
+
+/// A set of declared variables and their current values.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct Variables {
+    variables: HashMap<String, (Format, VariantValue)>,
+}
+
+impl Variables {
+    /// Creates a set of variables from their declarations,
+    /// each holding the default value for its format.
+    pub fn from_declarations<'a>(
+        declarations: impl IntoIterator<Item = (&'a str, &'a Format)>,
+    ) -> Self {
+        let variables = declarations
+            .into_iter()
+            .map(|(name, format)| (name.to_string(), (format.clone(), format.default_value())))
+            .collect();
+        Variables { variables }
+    }
+
+    /// Returns the format a variable was declared with.
+    pub fn format(&self, name: &str) -> Option<&Format> {
+        self.variables.get(name).map(|(format, _)| format)
+    }
+
+    /// Returns the current value of a variable.
+    pub fn get(&self, name: &str) -> Option<&VariantValue> {
+        self.variables.get(name).map(|(_, value)| value)
+    }
+
+    /// Changes the value of a declared variable,
+    /// failing if the variable doesn't exist or if the value doesn't fit its format.
+    /// Ints stored in Float variables become Floats.
+    ///
+    /// The formats of resources the variable's format refers to are read from `database`.
+    pub fn set(
+        &mut self,
+        name: &str,
+        value: VariantValue,
+        database: &ResourceDatabase,
+    ) -> Result<(), RuntimeError> {
+        let (format, current) = self
+            .variables
+            .get_mut(name)
+            .ok_or_else(|| RuntimeError::UndeclaredVariable(name.to_string()))?;
+
+        *current = conform_value(value, format, database).map_err(|problems| {
+            RuntimeError::MismatchedVariableType {
+                variable: name.to_string(),
+                expected: format.clone(),
+                problems,
+            }
+        })?;
+        Ok(())
+    }
+}
+
+impl<'a> From<&'a [VariableDeclaration]> for Variables {
+    fn from(declarations: &'a [VariableDeclaration]) -> Self {
+        Variables::from_declarations(
+            declarations
+                .iter()
+                .map(|declaration| (declaration.name.as_str(), &declaration.format)),
+        )
+    }
+}
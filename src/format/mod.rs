@@ -0,0 +1,543 @@
+//! # Formats & Types
+//!
+//! A format describes the data structure of a custom resource, like an object's property,
+//! a custom recipe, or game data.
+//!
+//! ## Base Formats
+//!
+//! The engine comes with a few base formats:
+//! - `Int`: whole numbers, without a fractional part;
+//! - `Float`: numbers that might have a fractional part;
+//! - `Text`: some text, of any length;
+//! - `Truth`: a truth about some statement, like the response to a yes/no question.
+//!
+//! ## Composite Formats
+//!
+//! You can compose base types using so-called "algebraic data-structures." Since you're reading
+//! this documentation written in Rust, you probably know what they are.
+//!
+//! ### Structs
+//!
+//! Structs can compose formats linearly, aggregating two formats side by side to create a bigger format:
+//!
+//! ```text
+//! --- struct ---
+//!  name: Text
+//!  age: Int
+//! --------------
+//! ```
+//!
+//! ### Eithers
+//!
+//! Eithers can compose formats alternatively: it describes a choice between one of many cases.
+//!
+//! ```text
+//! --- either ---
+//! chapter_1
+//! chapter_2
+//! chapter_3
+//! --------------
+//! ```
+//!
+//! Each either variant can also have its own associated data: this makes sense if you are in `chapter_1`,
+//! there is no `chapter_2` data available and vice-versa.
+//!
+//! ## Resources & Recipes
+//!
+//! Any resource format is also a valid type, like, for example:
+//! Item, Attack, Enemy, Character and even Recipe are valid types.
+//!
+//! Also, any _recipe_ is a valid type - and describes objects that
+//! were baked from that recipe.
+//!
+//! ## Parametric Formats
+//!
+//! Parametric formats are formats which are "incomplete" on their own:
+//! they need a _format parameter_ to fully describe the data structure.
+//!
+//! ### Lists
+//!
+//! A list is a collection of items that can grow and shrink as needed. A list
+//! can contain items which all have a particular format.
+//!
+//! ### Maybe
+//!
+//! A maybe is a collection that contains one item - possibly. You can do different
+//! things depending on whether an item is present or not.
+//!
+//! ### Maps
+//!
+//! A map is a collection of items that can be looked up by a text key, like a dictionary.
+//! All items of a map have a particular format.
+//!
+//! ### Your own parametric formats
+//!
+//! A format definition can declare its own format parameters, and use them in its expression.
+//! For example, a `Stack<T>` can be defined as a tuple containing a `List<T>` of items and an
+//! `Int` with the stack's capacity. Other formats then use it by filling in its parameters: `Stack<Item>`.
+
+use crate::project::resource::ExternalResource;
+use ordermap::OrderMap;
+use serde::{Deserialize, Serialize};
+use std::{any::TypeId, collections::HashMap};
+use thiserror::Error;
+
+pub mod validate;
+
+/// A Resource that describes how to interpret a bit of custom data.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FormatDefinition {
+    name: String,
+    description: Option<String>,
+    /// The names of the format parameters of this definition, if it's parametric.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    parameters: Vec<String>,
+    #[serde(flatten)]
+    expression: Format,
+}
+
+impl FormatDefinition {
+    /// Creates a new format definition without format parameters.
+    pub fn new(name: String, description: Option<String>, expression: Format) -> Self {
+        FormatDefinition {
+            name,
+            description,
+            parameters: Vec::new(),
+            expression,
+        }
+    }
+
+    /// Creates a new format definition with format parameters.
+    pub fn new_parametric(
+        name: String,
+        description: Option<String>,
+        parameters: Vec<String>,
+        expression: Format,
+    ) -> Self {
+        FormatDefinition {
+            name,
+            description,
+            parameters,
+            expression,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub fn parameters(&self) -> &[String] {
+        &self.parameters
+    }
+
+    pub fn expression(&self) -> &Format {
+        &self.expression
+    }
+
+    /// Returns the format this definition describes, with its parameters replaced by `arguments`.
+    pub fn instantiate(&self, arguments: &[Format]) -> Result<Format, FormatError> {
+        if arguments.len() != self.parameters.len() {
+            return Err(FormatError::WrongArgumentCount {
+                format: self.name.clone(),
+                expected: self.parameters.len(),
+                found: arguments.len(),
+            });
+        }
+
+        let arguments = self
+            .parameters
+            .iter()
+            .map(String::as_str)
+            .zip(arguments)
+            .collect();
+        self.expression.substitute(&arguments)
+    }
+}
+
+/// Error for when a parametric format can't be filled in.
+#[derive(Debug, Error)]
+pub enum FormatError {
+    #[error("format `{format}` takes {expected} format parameters, but {found} were given")]
+    WrongArgumentCount {
+        format: String,
+        expected: usize,
+        found: usize,
+    },
+    #[error("format parameter `{0}` was not declared")]
+    UnknownParameter(String),
+}
+
+/// A tree that describes the type thoroughly.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "content", rename_all = "snake_case")]
+pub enum Format {
+    Tuple(Vec<(String, Format)>),
+    Either(Vec<(String, Format)>),
+    /// A collection of items of the same format.
+    List(Box<Format>),
+    /// A single item of a format, or nothing.
+    Maybe(Box<Format>),
+    /// A collection of items of the same format, each under a text key.
+    Map(Box<Format>),
+    BaseType(BaseType),
+    External(ExternalResource),
+    /// A format parameter of the definition this format is part of, like the `T` in `Stack<T>`.
+    Parameter(String),
+    /// A parametric format definition with its parameters filled in, like `Stack<Item>`.
+    Generic {
+        format: ExternalResource,
+        arguments: Vec<Format>,
+    },
+}
+
+impl Format {
+    /// Returns this format with its format parameters replaced by the given formats.
+    pub fn substitute(&self, arguments: &HashMap<&str, &Format>) -> Result<Format, FormatError> {
+        let substitute_all = |formats: &[(String, Format)]| {
+            formats
+                .iter()
+                .map(|(name, format)| Ok((name.clone(), format.substitute(arguments)?)))
+                .collect::<Result<_, FormatError>>()
+        };
+
+        Ok(match self {
+            Format::Tuple(fields) => Format::Tuple(substitute_all(fields)?),
+            Format::Either(cases) => Format::Either(substitute_all(cases)?),
+            Format::List(item) => Format::List(Box::new(item.substitute(arguments)?)),
+            Format::Maybe(item) => Format::Maybe(Box::new(item.substitute(arguments)?)),
+            Format::Map(item) => Format::Map(Box::new(item.substitute(arguments)?)),
+            Format::BaseType(_) | Format::External(_) => self.clone(),
+            Format::Parameter(name) => arguments
+                .get(name.as_str())
+                .map(|format| (*format).clone())
+                .ok_or_else(|| FormatError::UnknownParameter(name.clone()))?,
+            Format::Generic {
+                format,
+                arguments: inner,
+            } => Format::Generic {
+                format: format.clone(),
+                arguments: inner
+                    .iter()
+                    .map(|argument| argument.substitute(arguments))
+                    .collect::<Result<_, _>>()?,
+            },
+        })
+    }
+
+    /// Returns the value something of this format holds before anything is assigned to it.
+    ///
+    /// The default value of an either is its first case.
+    pub fn default_value(&self) -> VariantValue {
+        match self {
+            Format::Tuple(fields) => VariantValue::Tuple {
+                fields: fields
+                    .iter()
+                    .map(|(name, format)| (name.clone(), format.default_value()))
+                    .collect(),
+            },
+            Format::Either(cases) => match cases.first() {
+                Some((case, format)) => VariantValue::Case {
+                    case: case.clone(),
+                    value: Box::new(format.default_value()),
+                },
+                None => VariantValue::Void,
+            },
+            Format::List(_) => VariantValue::List(Vec::new()),
+            Format::Maybe(_) => VariantValue::Maybe { maybe: None },
+            // Maps are held in tuples, one field for each key.
+            Format::Map(_) => VariantValue::Tuple {
+                fields: OrderMap::new(),
+            },
+            Format::BaseType(base_type) => base_type.default_value(),
+            Format::External(_) | Format::Parameter(_) | Format::Generic { .. } => {
+                VariantValue::Void
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let write_all =
+            |f: &mut std::fmt::Formatter<'_>, formats: &[(String, Format)], separator| {
+                for (index, (name, format)) in formats.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, "{}", separator)?;
+                    }
+                    write!(f, "{}: {}", name, format)?;
+                }
+                Ok(())
+            };
+
+        match self {
+            Format::Tuple(fields) => {
+                write!(f, "(")?;
+                write_all(f, fields, ", ")?;
+                write!(f, ")")
+            }
+            Format::Either(cases) => {
+                write!(f, "(")?;
+                write_all(f, cases, " | ")?;
+                write!(f, ")")
+            }
+            Format::List(item) => write!(f, "List<{}>", item),
+            Format::Maybe(item) => write!(f, "Maybe<{}>", item),
+            Format::Map(item) => write!(f, "Map<{}>", item),
+            Format::BaseType(base_type) => write!(f, "{:?}", base_type),
+            Format::External(resource) => write!(f, "{}", resource.uuid),
+            Format::Parameter(name) => write!(f, "{}", name),
+            Format::Generic { format, arguments } => {
+                write!(f, "{}<", format.uuid)?;
+                for (index, argument) in arguments.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", argument)?;
+                }
+                write!(f, ">")
+            }
+        }
+    }
+}
+
+/// A base type from the engine; a primitive.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BaseType {
+    Void,
+    Int,
+    Float,
+    Text,
+    Truth,
+}
+
+impl BaseType {
+    pub fn type_id(&self) -> TypeId {
+        match self {
+            BaseType::Void => TypeId::of::<()>(),
+            BaseType::Int => TypeId::of::<i32>(),
+            BaseType::Float => TypeId::of::<f32>(),
+            BaseType::Text => TypeId::of::<String>(),
+            BaseType::Truth => TypeId::of::<bool>(),
+        }
+    }
+
+    /// Returns the value something of this type holds before anything is assigned to it.
+    pub fn default_value(&self) -> VariantValue {
+        match self {
+            BaseType::Void => VariantValue::Void,
+            BaseType::Int => VariantValue::Int(0),
+            BaseType::Float => VariantValue::Float(0.0),
+            BaseType::Text => VariantValue::Text(String::new()),
+            BaseType::Truth => VariantValue::Truth(false),
+        }
+    }
+}
+
+/// Describes a value.
+///
+/// Values are serialized as plain JSON values (`null`, numbers, strings, booleans and arrays).
+/// Numbers without a fractional part are always read as an [`VariantValue::Int`],
+/// which is why floats are always written with one (`1.0` instead of `1`).
+/// JSON has no way to write floats that aren't finite (NaN and infinities), so those fail to
+/// serialize instead of being read back as something else.
+///
+/// Structured values are serialized as objects with a distinct set of keys
+/// (`{ "fields": ... }`, `{ "case": ..., "value": ... }`, `{ "maybe": ... }` and `{ "resource": ... }`),
+/// so that they can't be mistaken for each other. As `Void` is written as `null` too, a maybe that
+/// holds `Void` is read back as an empty one.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", untagged, deny_unknown_fields)]
+pub enum VariantValue {
+    /// A value that carries no information.
+    Void,
+    /// An integer number (with 32 bits).
+    Int(i32),
+    /// A number that might have a fractional part (with 32 bits).
+    #[serde(serialize_with = "serialize_finite")]
+    Float(f32),
+    /// A truth value, either `true` or `false`.
+    Truth(bool),
+    /// Some text, of any length.
+    Text(String),
+    /// A collection of values, in order.
+    List(Vec<VariantValue>),
+    /// A value made of named fields, side by side.
+    Tuple {
+        fields: OrderMap<String, VariantValue>,
+    },
+    /// One case out of many, with the data associated to that case.
+    Case {
+        case: String,
+        value: Box<VariantValue>,
+    },
+    /// A value that might not be there.
+    Maybe {
+        // Without this, a missing `maybe` would be read as `None`, and any object would be a maybe.
+        #[serde(deserialize_with = "Option::deserialize")]
+        maybe: Option<Box<VariantValue>>,
+    },
+    /// A reference to a resource.
+    Resource { resource: ExternalResource },
+}
+
+/// Serializes a float, failing if it isn't finite.
+fn serialize_finite<S: serde::Serializer>(float: &f32, serializer: S) -> Result<S::Ok, S::Error> {
+    if !float.is_finite() {
+        return Err(serde::ser::Error::custom(format!(
+            "{float} can't be saved, as it isn't a finite number"
+        )));
+    }
+    serializer.serialize_f32(*float)
+}
+
+impl VariantValue {
+    /// Returns what kind of value this is.
+    pub fn kind(&self) -> ValueKind {
+        match self {
+            VariantValue::Void => ValueKind::Void,
+            VariantValue::Int(_) => ValueKind::Int,
+            VariantValue::Float(_) => ValueKind::Float,
+            VariantValue::Truth(_) => ValueKind::Truth,
+            VariantValue::Text(_) => ValueKind::Text,
+            VariantValue::List(_) => ValueKind::List,
+            VariantValue::Tuple { .. } => ValueKind::Tuple,
+            VariantValue::Case { .. } => ValueKind::Case,
+            VariantValue::Maybe { .. } => ValueKind::Maybe,
+            VariantValue::Resource { .. } => ValueKind::Resource,
+        }
+    }
+}
+
+impl std::fmt::Display for VariantValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VariantValue::Void => Ok(()),
+            VariantValue::Int(int) => write!(f, "{}", int),
+            VariantValue::Float(float) => write!(f, "{}", float),
+            VariantValue::Truth(truth) => write!(f, "{}", truth),
+            VariantValue::Text(text) => write!(f, "{}", text),
+            VariantValue::List(items) => {
+                write!(f, "[")?;
+                for (index, item) in items.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+            VariantValue::Tuple { fields } => {
+                write!(f, "(")?;
+                for (index, (name, value)) in fields.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", name, value)?;
+                }
+                write!(f, ")")
+            }
+            VariantValue::Case { case, value } => match value.as_ref() {
+                VariantValue::Void => write!(f, "{}", case),
+                value => write!(f, "{}({})", case, value),
+            },
+            VariantValue::Maybe { maybe: Some(value) } => write!(f, "{}", value),
+            VariantValue::Maybe { maybe: None } => write!(f, "nothing"),
+            VariantValue::Resource { resource } => write!(f, "{}", resource.uuid),
+        }
+    }
+}
+
+/// The kind of a [`VariantValue`]: a base type, or the shape of a structured value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueKind {
+    Void,
+    Int,
+    Float,
+    Truth,
+    Text,
+    List,
+    Tuple,
+    Case,
+    Maybe,
+    Resource,
+}
+
+impl std::fmt::Display for ValueKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl From<BaseType> for ValueKind {
+    fn from(base_type: BaseType) -> Self {
+        match base_type {
+            BaseType::Void => ValueKind::Void,
+            BaseType::Int => ValueKind::Int,
+            BaseType::Float => ValueKind::Float,
+            BaseType::Text => ValueKind::Text,
+            BaseType::Truth => ValueKind::Truth,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn round_trip(value: VariantValue) {
+        let json = serde_json::to_string(&value).unwrap();
+        let read: VariantValue = serde_json::from_str(&json).unwrap();
+        assert_eq!(read, value, "{json} was read back as something else");
+    }
+
+    #[test]
+    fn values_round_trip() {
+        round_trip(VariantValue::Void);
+        round_trip(VariantValue::Int(-42));
+        round_trip(VariantValue::Float(1.0));
+        round_trip(VariantValue::Float(-2.5));
+        round_trip(VariantValue::Truth(true));
+        round_trip(VariantValue::Text("hello".into()));
+        round_trip(VariantValue::List(vec![
+            VariantValue::Int(1),
+            VariantValue::Text("two".into()),
+        ]));
+        round_trip(VariantValue::Tuple {
+            fields: OrderMap::from([
+                ("name".to_string(), VariantValue::Text("Ana".into())),
+                ("age".to_string(), VariantValue::Int(30)),
+            ]),
+        });
+        round_trip(VariantValue::Case {
+            case: "some".into(),
+            value: Box::new(VariantValue::Float(0.5)),
+        });
+        round_trip(VariantValue::Maybe {
+            maybe: Some(Box::new(VariantValue::Int(0))),
+        });
+        round_trip(VariantValue::Maybe { maybe: None });
+        round_trip(VariantValue::Resource {
+            resource: ExternalResource {
+                uuid: Uuid::nil(),
+                handle: None,
+            },
+        });
+    }
+
+    #[test]
+    fn non_finite_floats_fail_to_serialize() {
+        for float in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            assert!(serde_json::to_string(&VariantValue::Float(float)).is_err());
+            assert!(
+                serde_json::to_string(&VariantValue::List(vec![VariantValue::Float(float)]))
+                    .is_err()
+            );
+        }
+    }
+}
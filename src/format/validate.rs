@@ -0,0 +1,651 @@
+//! # Validation
+//!
+//! Checks whether some data actually has the shape a [`Format`] describes.
+//!
+//! Data can be validated either as JSON (for example, the data of a resource file before the game
+//! ever reads it) or as a [`VariantValue`]. Validation doesn't stop at the first problem: it
+//! reports every value that doesn't fit, each with the path where it was found, like
+//! `items[3].price: expected Int, found Text` (a `?` stands for the value a maybe holds, like in
+//! `pet?.age`).
+//!
+//! ## JSON representation
+//!
+//! Data files are written as plain JSON, guided by their format:
+//! - base types are written as `null`, numbers, strings and booleans;
+//! - tuples and maps are written as objects;
+//! - lists are written as arrays;
+//! - maybes are written as `null` (when empty) or as the value they hold;
+//! - eithers are written as `{ "case": "name", "value": ... }`, or simply as `"name"` if the case
+//!   carries no data;
+//! - references to resources that aren't formats are written as `{ "uuid": "..." }`.
+
+use super::{BaseType, Format, FormatDefinition, FormatError, ValueKind, VariantValue};
+use crate::project::resource::{
+    ExternalResource, ResourceData, ResourceDatabase, ResourceLoadError,
+};
+use ordermap::OrderMap;
+use serde_json::Value;
+use std::fmt::{Display, Formatter};
+use thiserror::Error;
+use uuid::Uuid;
+
+/// How many formats can be resolved inside each other before validation gives up.
+const MAX_DEPTH: usize = 256;
+
+/// A step on the way from a value to one of the values inside of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathSegment {
+    /// A field of a tuple, or the data of a case of an either.
+    Field(String),
+    /// An item of a list.
+    Index(usize),
+    /// An item of a map.
+    Key(String),
+    /// The value a maybe holds, when it holds one.
+    Something,
+}
+
+/// The path from a value to one of the values inside of it, like `items[3].price`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ValuePath(pub Vec<PathSegment>);
+
+impl Display for ValuePath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.0.is_empty() {
+            return write!(f, "value");
+        }
+        for (index, segment) in self.0.iter().enumerate() {
+            match segment {
+                PathSegment::Field(name) if index == 0 => write!(f, "{}", name)?,
+                PathSegment::Field(name) => write!(f, ".{}", name)?,
+                PathSegment::Index(item) => write!(f, "[{}]", item)?,
+                PathSegment::Key(key) => write!(f, "[{:?}]", key)?,
+                PathSegment::Something if index == 0 => write!(f, "value?")?,
+                PathSegment::Something => write!(f, "?")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A value that doesn't fit its format, and where it was found.
+#[derive(Debug, Error)]
+#[error("{path}: {problem}")]
+pub struct ValidationError {
+    pub path: ValuePath,
+    pub problem: ValidationProblem,
+}
+
+/// The reason why a value doesn't fit its format.
+#[derive(Debug, Error)]
+pub enum ValidationProblem {
+    #[error("expected {expected}, found {found}")]
+    Mismatched { expected: String, found: String },
+    #[error("{found} doesn't fit in {expected}")]
+    OutOfRange { expected: String, found: String },
+    #[error("missing field `{0}`")]
+    MissingField(String),
+    #[error("unexpected field `{0}`")]
+    UnknownField(String),
+    #[error("unknown case `{0}`")]
+    UnknownCase(String),
+    #[error("format {0} could not be loaded: {1}")]
+    UnresolvedFormat(Uuid, ResourceLoadError),
+    #[error("format parameter `{0}` was never filled in")]
+    UnfilledParameter(String),
+    #[error(transparent)]
+    Format(#[from] FormatError),
+    #[error("formats are nested too deeply (is a format defined as itself?)")]
+    TooDeep,
+}
+
+/// Checks a JSON value against a format, returning every problem found.
+pub fn validate_json(
+    value: &Value,
+    format: &Format,
+    database: &ResourceDatabase,
+) -> Vec<ValidationError> {
+    decode_json(value, format, database)
+        .err()
+        .unwrap_or_default()
+}
+
+/// Checks a JSON value against a format and, if it fits, reads it as a [`VariantValue`].
+pub fn decode_json(
+    value: &Value,
+    format: &Format,
+    database: &ResourceDatabase,
+) -> Result<VariantValue, Vec<ValidationError>> {
+    let mut validator = Validator::new(database);
+    let decoded = validator.json(value, format, 0);
+    if validator.errors.is_empty() {
+        Ok(decoded)
+    } else {
+        Err(validator.errors)
+    }
+}
+
+/// Checks a [`VariantValue`] against a format, returning every problem found.
+pub fn validate_value(
+    value: &VariantValue,
+    format: &Format,
+    database: &ResourceDatabase,
+) -> Vec<ValidationError> {
+    let mut validator = Validator::new(database);
+    validator.value(value, format, 0);
+    validator.errors
+}
+
+/// Checks a [`VariantValue`] against a format and, if it fits, returns it ready to be stored as
+/// something of that format.
+///
+/// Ints are turned into floats wherever the format expects a Float, the same way they're
+/// accepted by arithmetic and by the type checker. Format parameters that aren't filled in, like
+/// the `number` slots of a block that works with both Ints and Floats, accept any value: which
+/// format they stand for depends on where the block is used, which is the type checker's job.
+pub fn conform_value(
+    mut value: VariantValue,
+    format: &Format,
+    database: &ResourceDatabase,
+) -> Result<VariantValue, Vec<ValidationError>> {
+    let mut validator = Validator::new(database);
+    validator.open_parameters = true;
+    validator.coerce(&mut value, format, 0);
+    // Formats that can't be resolved are reported again while checking the value.
+    validator.errors.clear();
+    validator.value(&value, format, 0);
+    if validator.errors.is_empty() {
+        Ok(value)
+    } else {
+        Err(validator.errors)
+    }
+}
+
+/// Returns the JSON for the value something of a format holds before anything is assigned to
+/// it, like the data of a new resource.
+///
+/// References to resources can't have a default, so they're written as `null`.
+pub fn default_json(format: &Format, database: &ResourceDatabase) -> Value {
+    Validator::new(database).default_json(format, 0)
+}
+
+/// What a format means once it's resolved to something that can be checked directly.
+enum Resolved {
+    Format(Format),
+    /// The format refers to a resource that isn't a format: values must be references to it.
+    Reference,
+}
+
+struct Validator<'db> {
+    database: &'db ResourceDatabase,
+    path: Vec<PathSegment>,
+    errors: Vec<ValidationError>,
+    /// Whether format parameters that aren't filled in accept any value.
+    open_parameters: bool,
+}
+
+impl<'db> Validator<'db> {
+    fn new(database: &'db ResourceDatabase) -> Self {
+        Validator {
+            database,
+            path: Vec::new(),
+            errors: Vec::new(),
+            open_parameters: false,
+        }
+    }
+
+    fn report(&mut self, problem: ValidationProblem) {
+        self.errors.push(ValidationError {
+            path: ValuePath(self.path.clone()),
+            problem,
+        });
+    }
+
+    fn mismatched(&mut self, expected: &Format, found: impl Display) {
+        self.report(ValidationProblem::Mismatched {
+            expected: expected.to_string(),
+            found: found.to_string(),
+        });
+    }
+
+    /// Runs `check` with `segment` appended to the current path.
+    fn at<T>(&mut self, segment: PathSegment, check: impl FnOnce(&mut Self) -> T) -> T {
+        self.path.push(segment);
+        let result = check(self);
+        self.path.pop();
+        result
+    }
+
+    /// Loads the definition of an external format.
+    fn definition(&mut self, resource: &ExternalResource) -> Option<Option<FormatDefinition>> {
+        match self.database.get(&resource.uuid) {
+            Ok(handle) => match handle.lock_ref().data() {
+                ResourceData::Format(definition) => Some(Some(definition.clone())),
+                _ => Some(None),
+            },
+            Err(error) => {
+                self.report(ValidationProblem::UnresolvedFormat(resource.uuid, error));
+                None
+            }
+        }
+    }
+
+    /// Resolves external, generic and parameter formats until something checkable is left.
+    ///
+    /// Returns `None` if that's not possible, reporting a problem unless the format is a parameter
+    /// that accepts any value.
+    fn resolve(&mut self, format: &Format, depth: usize) -> Option<Resolved> {
+        if depth > MAX_DEPTH {
+            self.report(ValidationProblem::TooDeep);
+            return None;
+        }
+
+        match format {
+            Format::External(resource) => match self.definition(resource)? {
+                Some(definition) => match definition.instantiate(&[]) {
+                    Ok(format) => self.resolve(&format, depth + 1),
+                    Err(error) => {
+                        self.report(error.into());
+                        None
+                    }
+                },
+                None => Some(Resolved::Reference),
+            },
+            Format::Generic { format, arguments } => match self.definition(format)? {
+                Some(definition) => match definition.instantiate(arguments) {
+                    Ok(format) => self.resolve(&format, depth + 1),
+                    Err(error) => {
+                        self.report(error.into());
+                        None
+                    }
+                },
+                None => Some(Resolved::Reference),
+            },
+            Format::Parameter(_) if self.open_parameters => None,
+            Format::Parameter(name) => {
+                self.report(ValidationProblem::UnfilledParameter(name.clone()));
+                None
+            }
+            format => Some(Resolved::Format(format.clone())),
+        }
+    }
+
+    fn default_json(&mut self, format: &Format, depth: usize) -> Value {
+        let Some(Resolved::Format(format)) = self.resolve(format, depth) else {
+            return Value::Null;
+        };
+
+        match format {
+            Format::BaseType(BaseType::Void) => Value::Null,
+            Format::BaseType(BaseType::Int) => Value::from(0),
+            Format::BaseType(BaseType::Float) => Value::from(0.0),
+            Format::BaseType(BaseType::Text) => Value::from(""),
+            Format::BaseType(BaseType::Truth) => Value::from(false),
+            Format::Tuple(fields) => Value::Object(
+                fields
+                    .iter()
+                    .map(|(name, field)| (name.clone(), self.default_json(field, depth + 1)))
+                    .collect(),
+            ),
+            Format::Either(cases) => match cases.first() {
+                Some((case, format)) => serde_json::json!({
+                    "case": case,
+                    "value": self.default_json(format, depth + 1),
+                }),
+                None => Value::Null,
+            },
+            Format::List(_) => Value::Array(Vec::new()),
+            Format::Map(_) => Value::Object(Default::default()),
+            _ => Value::Null,
+        }
+    }
+
+    fn json(&mut self, value: &Value, format: &Format, depth: usize) -> VariantValue {
+        let format = match self.resolve(format, depth) {
+            Some(Resolved::Format(format)) => format,
+            Some(Resolved::Reference) => return self.json_reference(value, format),
+            None => return VariantValue::Void,
+        };
+
+        match (&format, value) {
+            (Format::BaseType(base_type), value) => self.json_base(value, base_type, &format),
+            (Format::Tuple(fields), Value::Object(object)) => {
+                for key in object.keys() {
+                    if !fields.iter().any(|(name, _)| name == key) {
+                        self.report(ValidationProblem::UnknownField(key.clone()));
+                    }
+                }
+                let fields = fields
+                    .iter()
+                    .map(|(name, field_format)| {
+                        let value = match object.get(name) {
+                            Some(value) => self.at(PathSegment::Field(name.clone()), |this| {
+                                this.json(value, field_format, depth + 1)
+                            }),
+                            None => {
+                                self.report(ValidationProblem::MissingField(name.clone()));
+                                VariantValue::Void
+                            }
+                        };
+                        (name.clone(), value)
+                    })
+                    .collect();
+                VariantValue::Tuple { fields }
+            }
+            (Format::Either(cases), Value::String(case)) => {
+                self.json_case(cases, case, &Value::Null, depth)
+            }
+            (Format::Either(cases), Value::Object(object)) => match object.get("case") {
+                Some(Value::String(case)) => {
+                    let value = object.get("value").unwrap_or(&Value::Null);
+                    self.json_case(cases, case, value, depth)
+                }
+                _ => {
+                    self.report(ValidationProblem::MissingField("case".into()));
+                    VariantValue::Void
+                }
+            },
+            (Format::List(item_format), Value::Array(items)) => VariantValue::List(
+                items
+                    .iter()
+                    .enumerate()
+                    .map(|(index, item)| {
+                        self.at(PathSegment::Index(index), |this| {
+                            this.json(item, item_format, depth + 1)
+                        })
+                    })
+                    .collect(),
+            ),
+            (Format::Maybe(_), Value::Null) => VariantValue::Maybe { maybe: None },
+            (Format::Maybe(item_format), value) => {
+                let item = self.at(PathSegment::Something, |this| {
+                    this.json(value, item_format, depth + 1)
+                });
+                VariantValue::Maybe {
+                    maybe: Some(Box::new(item)),
+                }
+            }
+            (Format::Map(item_format), Value::Object(object)) => VariantValue::Tuple {
+                fields: object
+                    .iter()
+                    .map(|(key, item)| {
+                        let item = self.at(PathSegment::Key(key.clone()), |this| {
+                            this.json(item, item_format, depth + 1)
+                        });
+                        (key.clone(), item)
+                    })
+                    .collect::<OrderMap<_, _>>(),
+            },
+            (format, value) => {
+                self.mismatched(format, json_kind(value));
+                VariantValue::Void
+            }
+        }
+    }
+
+    fn json_base(&mut self, value: &Value, base_type: &BaseType, format: &Format) -> VariantValue {
+        let decoded = match (base_type, value) {
+            (BaseType::Void, Value::Null) => Some(VariantValue::Void),
+            (BaseType::Int, Value::Number(number)) if json_kind(value) == ValueKind::Int => {
+                match number.as_i64().and_then(|int| i32::try_from(int).ok()) {
+                    Some(int) => Some(VariantValue::Int(int)),
+                    None => return self.out_of_range(format, number),
+                }
+            }
+            (BaseType::Float, Value::Number(number)) => {
+                match number.as_f64().map(|float| float as f32) {
+                    Some(float) if float.is_finite() => Some(VariantValue::Float(float)),
+                    _ => return self.out_of_range(format, number),
+                }
+            }
+            (BaseType::Text, Value::String(text)) => Some(VariantValue::Text(text.clone())),
+            (BaseType::Truth, Value::Bool(truth)) => Some(VariantValue::Truth(*truth)),
+            _ => None,
+        };
+
+        decoded.unwrap_or_else(|| {
+            self.mismatched(format, json_kind(value));
+            VariantValue::Void
+        })
+    }
+
+    fn out_of_range(&mut self, expected: &Format, found: impl Display) -> VariantValue {
+        self.report(ValidationProblem::OutOfRange {
+            expected: expected.to_string(),
+            found: found.to_string(),
+        });
+        VariantValue::Void
+    }
+
+    fn json_case(
+        &mut self,
+        cases: &[(String, Format)],
+        case: &str,
+        value: &Value,
+        depth: usize,
+    ) -> VariantValue {
+        match cases.iter().find(|(name, _)| name == case) {
+            Some((name, case_format)) => {
+                let value = self.at(PathSegment::Field(name.clone()), |this| {
+                    this.json(value, case_format, depth + 1)
+                });
+                VariantValue::Case {
+                    case: name.clone(),
+                    value: Box::new(value),
+                }
+            }
+            None => {
+                self.report(ValidationProblem::UnknownCase(case.to_string()));
+                VariantValue::Void
+            }
+        }
+    }
+
+    fn json_reference(&mut self, value: &Value, format: &Format) -> VariantValue {
+        match serde_json::from_value::<ExternalResource>(value.clone()) {
+            Ok(resource) => VariantValue::Resource { resource },
+            Err(_) => {
+                self.mismatched(format, json_kind(value));
+                VariantValue::Void
+            }
+        }
+    }
+
+    /// Turns the Ints of a value into Floats wherever its format expects a Float.
+    ///
+    /// Anything else that doesn't fit is left as it is, for [`Self::value`] to report.
+    fn coerce(&mut self, value: &mut VariantValue, format: &Format, depth: usize) {
+        let Some(Resolved::Format(format)) = self.resolve(format, depth) else {
+            return;
+        };
+
+        match (&format, value) {
+            (Format::BaseType(BaseType::Float), value) => {
+                if let VariantValue::Int(int) = *value {
+                    *value = VariantValue::Float(int as f32);
+                }
+            }
+            (Format::Tuple(fields), VariantValue::Tuple { fields: values }) => {
+                for (name, field_format) in fields {
+                    if let Some(value) = values.get_mut(name) {
+                        self.coerce(value, field_format, depth + 1);
+                    }
+                }
+            }
+            (Format::Either(cases), VariantValue::Case { case, value }) => {
+                if let Some((_, case_format)) = cases.iter().find(|(name, _)| name == case) {
+                    self.coerce(value, case_format, depth + 1);
+                }
+            }
+            (Format::List(item_format), VariantValue::List(items)) => {
+                for item in items {
+                    self.coerce(item, item_format, depth + 1);
+                }
+            }
+            (Format::Maybe(item_format), VariantValue::Maybe { maybe: Some(item) }) => {
+                self.coerce(item, item_format, depth + 1);
+            }
+            (Format::Map(item_format), VariantValue::Tuple { fields }) => {
+                for item in fields.values_mut() {
+                    self.coerce(item, item_format, depth + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn value(&mut self, value: &VariantValue, format: &Format, depth: usize) {
+        let format = match self.resolve(format, depth) {
+            Some(Resolved::Format(format)) => format,
+            Some(Resolved::Reference) => {
+                if !matches!(value, VariantValue::Resource { .. }) {
+                    self.mismatched(format, value.kind());
+                }
+                return;
+            }
+            None => return,
+        };
+
+        match (&format, value) {
+            (Format::BaseType(base_type), value)
+                if ValueKind::from(base_type.clone()) == value.kind() => {}
+            (Format::Tuple(fields), VariantValue::Tuple { fields: values }) => {
+                for key in values.keys() {
+                    if !fields.iter().any(|(name, _)| name == key) {
+                        self.report(ValidationProblem::UnknownField(key.clone()));
+                    }
+                }
+                for (name, field_format) in fields {
+                    match values.get(name) {
+                        Some(value) => self.at(PathSegment::Field(name.clone()), |this| {
+                            this.value(value, field_format, depth + 1)
+                        }),
+                        None => self.report(ValidationProblem::MissingField(name.clone())),
+                    }
+                }
+            }
+            (Format::Either(cases), VariantValue::Case { case, value }) => {
+                match cases.iter().find(|(name, _)| name == case) {
+                    Some((name, case_format)) => self
+                        .at(PathSegment::Field(name.clone()), |this| {
+                            this.value(value, case_format, depth + 1)
+                        }),
+                    None => self.report(ValidationProblem::UnknownCase(case.clone())),
+                }
+            }
+            (Format::List(item_format), VariantValue::List(items)) => {
+                for (index, item) in items.iter().enumerate() {
+                    self.at(PathSegment::Index(index), |this| {
+                        this.value(item, item_format, depth + 1)
+                    });
+                }
+            }
+            (Format::Maybe(_), VariantValue::Maybe { maybe: None }) => {}
+            (Format::Maybe(item_format), VariantValue::Maybe { maybe: Some(item) }) => self
+                .at(PathSegment::Something, |this| {
+                    this.value(item, item_format, depth + 1)
+                }),
+            (Format::Map(item_format), VariantValue::Tuple { fields }) => {
+                for (key, item) in fields {
+                    self.at(PathSegment::Key(key.clone()), |this| {
+                        this.value(item, item_format, depth + 1)
+                    });
+                }
+            }
+            (format, value) => self.mismatched(format, value.kind()),
+        }
+    }
+}
+
+/// Returns the name of what a JSON value would be read as, if it had no format.
+fn json_kind(value: &Value) -> ValueKind {
+    match value {
+        Value::Null => ValueKind::Void,
+        Value::Bool(_) => ValueKind::Truth,
+        Value::Number(number) if number.is_i64() || number.is_u64() => ValueKind::Int,
+        Value::Number(_) => ValueKind::Float,
+        Value::String(_) => ValueKind::Text,
+        Value::Array(_) => ValueKind::List,
+        Value::Object(_) => ValueKind::Tuple,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn problems(value: Value, format: &Format) -> Vec<String> {
+        validate_json(&value, format, &ResourceDatabase::default())
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn ints_out_of_range_are_reported_as_such() {
+        let format = Format::BaseType(BaseType::Int);
+        assert_eq!(
+            problems(json!(3000000000u64), &format),
+            ["value: 3000000000 doesn't fit in Int"]
+        );
+        assert_eq!(
+            problems(json!(-3000000000i64), &format),
+            ["value: -3000000000 doesn't fit in Int"]
+        );
+        assert_eq!(
+            problems(json!(1.5), &format),
+            ["value: expected Int, found Float"]
+        );
+        assert!(problems(json!(i32::MAX), &format).is_empty());
+    }
+
+    #[test]
+    fn problems_inside_maybes_point_inside_them() {
+        let format = Format::Tuple(vec![(
+            "pet".into(),
+            Format::Maybe(Box::new(Format::Tuple(vec![(
+                "age".into(),
+                Format::BaseType(BaseType::Int),
+            )]))),
+        )]);
+        assert!(problems(json!({ "pet": null }), &format).is_empty());
+        assert_eq!(
+            problems(json!({ "pet": { "age": "old" } }), &format),
+            ["pet?.age: expected Int, found Text"]
+        );
+
+        let value = VariantValue::Tuple {
+            fields: OrderMap::from([(
+                "pet".to_string(),
+                VariantValue::Maybe {
+                    maybe: Some(Box::new(VariantValue::Int(3))),
+                },
+            )]),
+        };
+        let errors = validate_value(&value, &format, &ResourceDatabase::default());
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            "pet?: expected (age: Int), found Int"
+        );
+    }
+
+    #[test]
+    fn ints_conform_to_floats() {
+        let format = Format::List(Box::new(Format::BaseType(BaseType::Float)));
+        let value = VariantValue::List(vec![VariantValue::Int(2), VariantValue::Float(0.5)]);
+        assert_eq!(
+            conform_value(value, &format, &ResourceDatabase::default()).unwrap(),
+            VariantValue::List(vec![VariantValue::Float(2.0), VariantValue::Float(0.5)])
+        );
+
+        let errors = conform_value(
+            VariantValue::Float(0.5),
+            &Format::BaseType(BaseType::Int),
+            &ResourceDatabase::default(),
+        )
+        .unwrap_err();
+        assert_eq!(errors[0].to_string(), "value: expected Int, found Float");
+    }
+}
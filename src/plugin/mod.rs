@@ -1,22 +1,302 @@
 //! # Plugin
 //!
-//! A third-party source of [`PluginContribution`]s: assets, recipes, behaviours, tools and
+//! A third-party source of [`PluginContributions`]: assets, recipes, behaviours, tools and
 //! possibly even custom Rust code.
+//!
+//! A plugin is a folder with a `plugin.json` manifest (its [`PluginMetadata`]). Projects find
+//! their plugins in their `plugins` folder, and in the folders their dependencies resolve to
+//! (see [`Project::resolve_dependencies`](crate::project::Project::resolve_dependencies)).
 
+use crate::behaviour::{
+    Block, BlockInstanceDescriptor, ReifyContext, ReifyError, TypedBlock,
+    composite::{CompositeBlock, CompositeBlockDefinition},
+    registry::BlockMetadata,
+};
+use ordermap::OrderMap;
 use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    path::{Component, Path, PathBuf},
+};
+use thiserror::Error;
+use uuid::Uuid;
+
+/// The name of the manifest file of a plugin.
+pub const MANIFEST_FILE: &str = "plugin.json";
+
+/// Creates a block contributed by a plugin from its descriptor.
+pub type BlockConstructor =
+    fn(&BlockInstanceDescriptor, &ReifyContext) -> Result<Box<dyn TypedBlock>, ReifyError<'static>>;
 
 /// Stores the loaded references to plugins (sourced from a plugin folder)
 #[derive(Debug, Default)]
 pub struct PluginDatabase {
     pub plugins: Vec<PluginRef>,
+    /// The Rust constructors registered for plugin blocks.
+    constructors: HashMap<BlockContributionRef, BlockConstructor>,
+    /// The metadata of the block types registered for plugin blocks.
+    registered_metadata: HashMap<BlockContributionRef, BlockMetadata>,
+    /// The plugins that were found but failed to load.
+    errors: Vec<PluginLoadError>,
+}
+
+/// Error for when a plugin that was found fails to load.
+#[derive(Debug, Error)]
+#[error("Failed to load the plugin at {}: {kind}", path.display())]
+pub struct PluginLoadError {
+    /// The folder of the plugin.
+    pub path: PathBuf,
+    pub kind: PluginLoadErrorKind,
+}
+
+/// The reason why a plugin failed to load.
+#[derive(Debug, Error)]
+pub enum PluginLoadErrorKind {
+    #[error("its manifest could not be read ({0})")]
+    File(#[from] std::io::Error),
+    #[error("its manifest is invalid ({0})")]
+    Deserialize(#[from] serde_json::Error),
+    #[error("another plugin with the id `{0}` was already loaded")]
+    DuplicateId(String),
+    #[error("it contributes `{}`, which is outside of its folder", .0.display())]
+    OutsidePath(PathBuf),
+}
+
+/// How a project uses one of its plugins, as saved in `project.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PluginState {
+    pub active: bool,
+}
+
+impl PluginDatabase {
+    /// Finds and loads every plugin in some folders, each plugin being a folder with a manifest.
+    ///
+    /// Plugins are active unless their state says otherwise. Plugins that fail to load are kept
+    /// as [`PluginDatabase::errors`].
+    pub fn from_directories<P: AsRef<Path>>(
+        directories: impl IntoIterator<Item = P>,
+        states: &OrderMap<String, PluginState>,
+    ) -> Self {
+        let mut database = PluginDatabase::default();
+
+        for directory in directories {
+            // A project doesn't need to have any plugins.
+            let Ok(entries) = std::fs::read_dir(directory.as_ref()) else {
+                continue;
+            };
+            let mut paths = entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.join(MANIFEST_FILE).is_file())
+                .collect::<Vec<_>>();
+            paths.sort();
+
+            for path in paths {
+                database.add_plugin_root(path, states);
+            }
+        }
+
+        database
+    }
+
+    /// Loads the plugin in a folder (its root), like the checkout of a dependency.
+    ///
+    /// The plugin is active unless its state says otherwise. Returns the plugin, unless it
+    /// failed to load, in which case it's kept as one of the [`PluginDatabase::errors`].
+    pub fn add_plugin_root(
+        &mut self,
+        path: PathBuf,
+        states: &OrderMap<String, PluginState>,
+    ) -> Option<&PluginRef> {
+        if let Some(index) = self.plugins.iter().position(|plugin| plugin.path == path) {
+            return self.plugins.get(index);
+        }
+
+        match PluginMetadata::load(&path) {
+            Ok(metadata) if self.get(&metadata.id).is_some() => {
+                self.errors.push(PluginLoadError {
+                    path,
+                    kind: PluginLoadErrorKind::DuplicateId(metadata.id),
+                });
+                None
+            }
+            Ok(metadata) => {
+                let active = states.get(&metadata.id).is_none_or(|state| state.active);
+                self.plugins.push(PluginRef::new(metadata, path, active));
+                self.plugins.last()
+            }
+            Err(kind) => {
+                self.errors.push(PluginLoadError { path, kind });
+                None
+            }
+        }
+    }
+
+    /// Loads the plugin in a folder like [`PluginDatabase::add_plugin_root`], except that a plugin
+    /// with the same id that is `replaceable` (like another version of a dependency) is replaced
+    /// instead of being reported as a duplicate.
+    pub fn replace_plugin_root(
+        &mut self,
+        path: PathBuf,
+        states: &OrderMap<String, PluginState>,
+        replaceable: impl Fn(&PluginRef) -> bool,
+    ) -> Option<&PluginRef> {
+        if let Ok(metadata) = PluginMetadata::load(&path)
+            && let Some(index) = self.plugins.iter().position(|plugin| {
+                plugin.metadata.id == metadata.id && plugin.path != path && replaceable(plugin)
+            })
+        {
+            self.plugins.remove(index);
+        }
+        self.add_plugin_root(path, states)
+    }
+
+    /// Returns the plugins that were found but failed to load.
+    pub fn errors(&self) -> &[PluginLoadError] {
+        &self.errors
+    }
+
+    /// Activates or deactivates a plugin, returning whether it's installed.
+    pub fn set_active(&mut self, plugin_id: &str, active: bool) -> bool {
+        match self
+            .plugins
+            .iter_mut()
+            .find(|plugin| plugin.metadata.id == plugin_id)
+        {
+            Some(plugin) => {
+                plugin.active = active;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the plugin with the given id, if it's installed.
+    pub fn get(&self, plugin_id: &str) -> Option<&PluginRef> {
+        self.plugins
+            .iter()
+            .find(|plugin| plugin.metadata.id == plugin_id)
+    }
+
+    /// Registers the Rust constructor of a block contributed by a plugin.
+    ///
+    /// Registered constructors take precedence over the composite definitions in a plugin's
+    /// metadata.
+    pub fn register_constructor(
+        &mut self,
+        plugin_id: impl Into<String>,
+        block_id: impl Into<String>,
+        constructor: BlockConstructor,
+    ) {
+        let contribution = BlockContributionRef {
+            plugin_id: plugin_id.into(),
+            block_id: block_id.into(),
+        };
+        self.constructors.insert(contribution, constructor);
+    }
+
+    /// Registers a block type (such as one made with [`crate::block_define`])
+    /// as a block contributed by a plugin.
+    ///
+    /// The block is described by its own metadata, even if the plugin's manifest describes it
+    /// too, or doesn't list it at all.
+    pub fn register_block<B: Block + TypedBlock + 'static>(
+        &mut self,
+        plugin_id: impl Into<String>,
+        block_id: impl Into<String>,
+    ) {
+        let contribution = BlockContributionRef {
+            plugin_id: plugin_id.into(),
+            block_id: block_id.into(),
+        };
+        self.registered_metadata
+            .insert(contribution.clone(), B::metadata());
+        self.constructors.insert(contribution, |descriptor, ctx| {
+            Ok(Box::new(B::from_descriptor(descriptor, ctx)?))
+        });
+    }
+
+    /// Returns the metadata of the blocks registered with [`PluginDatabase::register_block`],
+    /// in no particular order.
+    pub fn registered_blocks(
+        &self,
+    ) -> impl Iterator<Item = (&BlockContributionRef, &BlockMetadata)> {
+        self.registered_metadata.iter()
+    }
+
+    /// Creates a block contributed by a plugin from its descriptor.
+    ///
+    /// Fails if the plugin isn't installed or active, or if it doesn't contribute the block.
+    pub fn reify_block(
+        &self,
+        contribution: &BlockContributionRef,
+        descriptor: &BlockInstanceDescriptor,
+        ctx: &ReifyContext,
+    ) -> Result<Box<dyn TypedBlock>, ReifyError<'static>> {
+        let plugin = self
+            .get(&contribution.plugin_id)
+            .ok_or_else(|| ReifyError::PluginNotFound(contribution.plugin_id.clone()))?;
+        if !plugin.active {
+            return Err(ReifyError::PluginInactive(contribution.plugin_id.clone()));
+        }
+
+        if let Some(constructor) = self.constructors.get(contribution) {
+            return constructor(descriptor, ctx);
+        }
+
+        let block = plugin
+            .metadata
+            .plugin_contributions
+            .blocks
+            .iter()
+            .find(|block| block.id == contribution.block_id);
+        match block {
+            Some(BlockContribution {
+                metadata,
+                definition: Some(definition),
+                ..
+            }) => Ok(Box::new(CompositeBlock::from_descriptor(
+                metadata, definition, descriptor, ctx,
+            )?)),
+            _ => Err(ReifyError::UnknownPluginBlock(contribution.clone())),
+        }
+    }
 }
 
 /// A reference to a plugin
 pub struct PluginRef {
     metadata: PluginMetadata,
+    /// The folder the plugin is in.
+    path: PathBuf,
     active: bool,
 }
 
+impl PluginRef {
+    /// Creates a reference to a plugin from its metadata and the folder it's in.
+    pub fn new(metadata: PluginMetadata, path: PathBuf, active: bool) -> Self {
+        PluginRef {
+            metadata,
+            path,
+            active,
+        }
+    }
+
+    /// Returns the folder the plugin is in.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the metadata of the plugin.
+    pub fn metadata(&self) -> &PluginMetadata {
+        &self.metadata
+    }
+
+    /// Returns whether the project uses the plugin.
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+}
+
 impl std::fmt::Debug for PluginRef {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -30,7 +310,7 @@ impl std::fmt::Debug for PluginRef {
 }
 
 /// Metadata of a plugin for information purposes.
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PluginMetadata {
     name: String,
     id: String,
@@ -40,11 +320,175 @@ pub struct PluginMetadata {
     plugin_contributions: PluginContributions,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct PluginContributions {}
+impl PluginMetadata {
+    /// Loads the manifest of the plugin in a folder.
+    ///
+    /// Fails if the plugin contributes files that aren't inside of its folder.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, PluginLoadErrorKind> {
+        let text = std::fs::read_to_string(path.as_ref().join(MANIFEST_FILE))?;
+        let metadata: Self = serde_json::from_str(&text)?;
+        if let Some(path) = metadata
+            .plugin_contributions
+            .paths()
+            .find(|path| !is_inside_folder(path))
+        {
+            return Err(PluginLoadErrorKind::OutsidePath(path.clone()));
+        }
+        Ok(metadata)
+    }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    /// Returns the id the plugin's contributions are referred by.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Returns what the plugin contributes to projects that use it.
+    pub fn contributions(&self) -> &PluginContributions {
+        &self.plugin_contributions
+    }
+}
+
+/// Everything a plugin contributes to projects that use it.
+///
+/// Files are given relative to the plugin's folder. The resources a plugin contributes keep
+/// the UUIDs written in their files, but projects see them under [`namespaced_uuid`]s, so
+/// that they can't collide with the resources of the project or of other plugins.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PluginContributions {
+    /// The blocks the plugin adds to the palette.
+    #[serde(default)]
+    pub blocks: Vec<BlockContribution>,
+    /// The resource files of the formats the plugin defines.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub formats: Vec<PathBuf>,
+    /// The resource files of the object recipes the plugin provides.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub object_recipes: Vec<PathBuf>,
+    /// The resource files of the rooms the plugin provides.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rooms: Vec<PathBuf>,
+    /// The resource files of the behaviours the plugin provides.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub behaviours: Vec<PathBuf>,
+    /// The resource files of the custom blocks the plugin defines.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub custom_blocks: Vec<PathBuf>,
+    /// Folders of images, sounds and other assets the plugin provides.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub assets: Vec<PathBuf>,
+    /// The tools the plugin adds to the editor.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<ToolContribution>,
+}
+
+impl PluginContributions {
+    /// Returns every resource file the plugin contributes.
+    pub fn resources(&self) -> impl Iterator<Item = &PathBuf> {
+        self.formats
+            .iter()
+            .chain(&self.object_recipes)
+            .chain(&self.rooms)
+            .chain(&self.behaviours)
+            .chain(&self.custom_blocks)
+    }
+
+    /// Returns every file and folder the plugin contributes: its resources, assets and tools.
+    pub fn paths(&self) -> impl Iterator<Item = &PathBuf> {
+        self.resources()
+            .chain(&self.assets)
+            .chain(self.tools.iter().map(|tool| &tool.path))
+    }
+}
+
+/// Returns whether a path, relative to some folder, leads to something inside of that folder.
+///
+/// Such paths only go down into folders: they can't be absolute or go up with `..`.
+pub(crate) fn is_inside_folder(path: &Path) -> bool {
+    let goes_down = path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+    let leaves_folder = path
+        .components()
+        .any(|component| matches!(component, Component::Normal(_)));
+    goes_down && leaves_folder
+}
+
+/// A tool contributed by a plugin, that the editor can run on a project.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolContribution {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// The program that runs the tool, relative to the plugin's folder.
+    pub path: PathBuf,
+}
+
+/// The namespace the UUIDs of plugin resources are derived in (see [`namespaced_uuid`]).
+pub const PLUGIN_NAMESPACE: Uuid = uuid::uuid!("15118d36-4e3b-4cda-9f9a-9adb22cce4e5");
+
+/// Returns the UUID a project sees a resource contributed by a plugin under.
+///
+/// The UUID is derived (as a version 5 UUID) from the plugin id and the UUID the resource has in
+/// its file, so it is the same in every project that uses the plugin.
+pub fn namespaced_uuid(plugin_id: &str, uuid: &Uuid) -> Uuid {
+    Uuid::new_v5(&PLUGIN_NAMESPACE, format!("{plugin_id}/{uuid}").as_bytes())
+}
+
+/// A block contributed by a plugin, referred to as `plugin_id:block_id`.
+///
+/// The block is either made out of other blocks, as described by its definition,
+/// or created by a Rust constructor registered in the [`PluginDatabase`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockContribution {
+    pub id: String,
+    #[serde(flatten)]
+    pub metadata: BlockMetadata,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub definition: Option<CompositeBlockDefinition>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct BlockContributionRef {
     pub plugin_id: String,
     pub block_id: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn load_with(contributions: serde_json::Value) -> Result<PluginMetadata, PluginLoadErrorKind> {
+        let temp = tempfile::tempdir().unwrap();
+        let manifest = json!({
+            "name": "Kit",
+            "id": "kit",
+            "description": "",
+            "version": "0.1.0",
+            "authors": [],
+            "plugin_contributions": contributions
+        });
+        std::fs::write(temp.path().join(MANIFEST_FILE), manifest.to_string()).unwrap();
+        PluginMetadata::load(temp.path())
+    }
+
+    #[test]
+    fn rejects_contributions_outside_of_the_plugin() {
+        assert!(
+            load_with(json!({ "formats": ["formats/stats.json"], "assets": ["./art"] })).is_ok()
+        );
+
+        let outside = [
+            json!({ "formats": ["../project.json"] }),
+            json!({ "assets": ["/home"] }),
+            json!({ "tools": [{ "id": "sh", "name": "Shell", "path": "tools/../../sh" }] }),
+        ];
+        for contributions in outside {
+            assert!(matches!(
+                load_with(contributions),
+                Err(PluginLoadErrorKind::OutsidePath(_))
+            ));
+        }
+    }
+}
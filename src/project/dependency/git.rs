@@ -0,0 +1,195 @@
+//! # Git dependencies
+//!
+//! Each repository is fetched into a bare *database* in the cache, shared by every commit of it
+//! that is used. Each pinned commit is then checked out into a folder of its own, which is the
+//! plugin root of the dependency:
+//!
+//! ```text
+//! <cache>/git/db/<repository>/
+//! <cache>/git/checkouts/<repository>/<commit>/
+//! ```
+//!
+//! Repositories are only fetched when the pinned commit isn't in their database already, and
+//! checkouts are only made again when they were changed.
+
+use super::DependencyError;
+use crate::project::GitDependency;
+use git2::{Oid, Repository, build::CheckoutBuilder};
+use std::path::{Path, PathBuf};
+
+/// What is fetched from repositories.
+const REFSPECS: &[&str] = &["+refs/heads/*:refs/heads/*", "+refs/tags/*:refs/tags/*"];
+
+/// Fetches a git dependency into a cache, checks out its commit and returns the checkout.
+pub fn resolve(cache_path: &Path, dependency: &GitDependency) -> Result<PathBuf, DependencyError> {
+    let oid = commit_oid(&dependency.commit_hash)?;
+    let key = repository_key(dependency.repository_url.as_str());
+    let database_path = cache_path.join("git").join("db").join(&key);
+    let checkout_path = checkout_path(cache_path, dependency);
+
+    if checkout_path.exists() {
+        if verify(&checkout_path, oid).is_ok() {
+            return Ok(checkout_path);
+        }
+        std::fs::remove_dir_all(&checkout_path)?;
+    }
+
+    let database = match Repository::open_bare(&database_path) {
+        Ok(database) => database,
+        Err(_) => {
+            std::fs::create_dir_all(&database_path)?;
+            Repository::init_bare(&database_path)?
+        }
+    };
+    if database.find_commit(oid).is_err() {
+        database
+            .remote_anonymous(dependency.repository_url.as_str())?
+            .fetch(REFSPECS, None, None)?;
+    }
+    // Fails if the repository doesn't have the commit.
+    database.find_commit(oid)?;
+
+    std::fs::create_dir_all(&checkout_path)?;
+    let checkout = Repository::init(&checkout_path)?;
+    checkout
+        .remote_anonymous(&database_path.to_string_lossy())?
+        .fetch(REFSPECS, None, None)?;
+    checkout.set_head_detached(oid)?;
+    checkout.checkout_head(Some(CheckoutBuilder::new().force()))?;
+
+    verify(&checkout_path, oid)?;
+    Ok(checkout_path)
+}
+
+/// Returns the folder a git dependency is checked out to.
+pub fn checkout_path(cache_path: &Path, dependency: &GitDependency) -> PathBuf {
+    cache_path
+        .join("git")
+        .join("checkouts")
+        .join(repository_key(dependency.repository_url.as_str()))
+        .join(&dependency.commit_hash)
+}
+
+/// Parses a full commit hash; short hashes are ambiguous, so they aren't accepted.
+fn commit_oid(commit_hash: &str) -> Result<Oid, DependencyError> {
+    match Oid::from_str(commit_hash) {
+        Ok(oid) if commit_hash.len() == 40 => Ok(oid),
+        _ => Err(DependencyError::InvalidCommitHash(commit_hash.to_string())),
+    }
+}
+
+/// Checks that a checkout is at a commit, and that none of its files were changed.
+fn verify(checkout_path: &Path, oid: Oid) -> Result<(), DependencyError> {
+    let repository = Repository::open(checkout_path)?;
+    let head = repository.head()?.peel_to_commit()?.id();
+    if head != oid || !repository.statuses(None)?.is_empty() {
+        return Err(DependencyError::Unverified {
+            path: checkout_path.to_path_buf(),
+            expected: oid.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Names the cache folders of a repository after its last path segment and a hash of its URL,
+/// so that repositories with the same name don't collide.
+fn repository_key(url: &str) -> String {
+    let name = url
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or_default()
+        .trim_end_matches(".git");
+    let name = name
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' => c,
+            _ => '_',
+        })
+        .collect::<String>();
+    let hash = Oid::hash_object(git2::ObjectType::Blob, url.as_bytes())
+        .map(|oid| oid.to_string())
+        .unwrap_or_default();
+    format!("{name}-{}", &hash[..hash.len().min(16)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::Signature;
+    use url::Url;
+
+    /// Makes a repository with a single commit, and returns it with the commit.
+    fn repository_with_commit(path: &Path) -> (Url, Oid) {
+        let repository = Repository::init(path).unwrap();
+        std::fs::write(path.join("plugin.json"), "{}").unwrap();
+        let mut index = repository.index().unwrap();
+        index.add_path(Path::new("plugin.json")).unwrap();
+        let tree = repository.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("Tester", "tester@example.com").unwrap();
+        let oid = repository
+            .commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                "Add manifest",
+                &tree,
+                &[],
+            )
+            .unwrap();
+        (Url::from_directory_path(path).unwrap(), oid)
+    }
+
+    #[test]
+    fn resolves_a_pinned_commit() {
+        let temp = tempfile::tempdir().unwrap();
+        let (url, oid) = repository_with_commit(&temp.path().join("repository"));
+        let cache = temp.path().join("cache");
+        let dependency = GitDependency::new(url, oid.to_string());
+
+        let root = resolve(&cache, &dependency).unwrap();
+        assert_eq!(root, checkout_path(&cache, &dependency));
+        assert!(root.join("plugin.json").is_file());
+        verify(&root, oid).unwrap();
+    }
+
+    #[test]
+    fn rejects_bad_hashes() {
+        let temp = tempfile::tempdir().unwrap();
+        let (url, oid) = repository_with_commit(&temp.path().join("repository"));
+        let cache = temp.path().join("cache");
+
+        let short = GitDependency::new(url.clone(), &oid.to_string()[..7]);
+        assert!(matches!(
+            resolve(&cache, &short),
+            Err(DependencyError::InvalidCommitHash(_))
+        ));
+        let unknown = GitDependency::new(url, "0123456789abcdef0123456789abcdef01234567");
+        assert!(matches!(
+            resolve(&cache, &unknown),
+            Err(DependencyError::Git(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_dirty_checkouts() {
+        let temp = tempfile::tempdir().unwrap();
+        let (url, oid) = repository_with_commit(&temp.path().join("repository"));
+        let cache = temp.path().join("cache");
+        let dependency = GitDependency::new(url, oid.to_string());
+
+        let root = resolve(&cache, &dependency).unwrap();
+        std::fs::write(root.join("plugin.json"), "{ \"changed\": true }").unwrap();
+        assert!(matches!(
+            verify(&root, oid),
+            Err(DependencyError::Unverified { .. })
+        ));
+
+        // Resolving again checks the commit out anew.
+        let root = resolve(&cache, &dependency).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(root.join("plugin.json")).unwrap(),
+            "{}"
+        );
+    }
+}
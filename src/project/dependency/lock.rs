@@ -0,0 +1,83 @@
+//! # Lockfile
+//!
+//! The `project.lock` file, next to `project.json`, records exactly what the dependencies of a
+//! project resolved to: the version picked for each marketplace package and the commit of each
+//! git dependency, along with the [`content_hash`](super::content_hash) of their files.
+//!
+//! Loading a project loads exactly what its lockfile records, and resolving dependencies keeps
+//! the locked versions as long as they still fit what the project requires, so that everyone
+//! working on a project uses the same plugins.
+
+use super::DependencyError;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use url::Url;
+
+/// The name of the lockfile of a project.
+pub const LOCK_FILE: &str = "project.lock";
+
+/// What the dependencies of a project resolved to.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lockfile {
+    #[serde(default)]
+    pub packages: Vec<LockedPackage>,
+}
+
+/// A dependency of a project, as it was resolved.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "source")]
+pub enum LockedPackage {
+    Marketplace {
+        marketplace_id: String,
+        version: Version,
+        checksum: String,
+    },
+    Git {
+        repository_url: Url,
+        commit_hash: String,
+        checksum: String,
+    },
+}
+
+impl Lockfile {
+    /// Loads a lockfile, if there's one.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Option<Self>, DependencyError> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => Ok(Some(serde_json::from_str(&text)?)),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Saves the lockfile.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), DependencyError> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+
+    /// Returns the locked version of a marketplace package and its checksum, if it's locked.
+    pub fn marketplace(&self, id: &str) -> Option<(&Version, &str)> {
+        self.packages.iter().find_map(|package| match package {
+            LockedPackage::Marketplace {
+                marketplace_id,
+                version,
+                checksum,
+            } if marketplace_id == id => Some((version, checksum.as_str())),
+            _ => None,
+        })
+    }
+
+    /// Returns the checksum of a commit of a git repository, if it's locked.
+    pub fn git(&self, url: &Url, commit: &str) -> Option<&str> {
+        self.packages.iter().find_map(|package| match package {
+            LockedPackage::Git {
+                repository_url,
+                commit_hash,
+                checksum,
+            } if repository_url == url && commit_hash == commit => Some(checksum.as_str()),
+            _ => None,
+        })
+    }
+}
@@ -0,0 +1,304 @@
+//! # Dependencies
+//!
+//! Resolving the [`ProjectDependency`]s of a project means downloading each of them into a
+//! cache, which is shared by every project of the user, and finding the folder of the plugin it
+//! provides (its *root*).
+//!
+//! Git dependencies are pinned to a commit, and marketplace dependencies are resolved against a
+//! [`Registry`]. What they resolved to is recorded in a [`Lockfile`].
+
+use super::{GitDependency, MarketplaceDependency, ProjectDependency};
+use lock::{LockedPackage, Lockfile};
+use registry::{Registry, Requirement, ResolvedPackage};
+use semver::Version;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use walkdir::WalkDir;
+
+pub mod git;
+pub mod lock;
+pub mod registry;
+
+/// The environment variable that overrides where dependencies are cached.
+pub const CACHE_VARIABLE: &str = "RPG_BAKER_CACHE";
+
+/// Error for when a dependency can't be resolved.
+#[derive(Debug, Error)]
+pub enum DependencyError {
+    #[error("Failed to access the dependency cache ({0})")]
+    File(#[from] std::io::Error),
+    #[error("Git failed to fetch the dependency ({0})")]
+    Git(#[from] git2::Error),
+    #[error("`{0}` is not a full commit hash")]
+    InvalidCommitHash(String),
+    #[error("The checkout at {} is not at commit {expected}", path.display())]
+    Unverified { path: PathBuf, expected: String },
+    #[error("The index of a registry is invalid ({0})")]
+    InvalidIndex(#[from] serde_json::Error),
+    #[error("No registry was given to resolve dependencies from the marketplace")]
+    NoRegistry,
+    #[error("There is no folder to cache dependencies in")]
+    NoCache,
+    #[error("The package `{0}` is not in the registry")]
+    PackageNotFound(String),
+    #[error("`{0}` is not a valid package id")]
+    InvalidPackageId(String),
+    #[error("The files of a package can't be at {} in a registry", .0.display())]
+    InvalidPackagePath(PathBuf),
+    #[error(
+        "No version of the package `{package}` fits {}",
+        requirements
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ")
+    )]
+    Conflict {
+        package: String,
+        requirements: Vec<Requirement>,
+    },
+    #[error("The files of the package `{package}` {version} are not the ones that were published")]
+    ChecksumMismatch { package: String, version: Version },
+    #[error("The files of `{package}` changed since they were locked")]
+    LockMismatch { package: String },
+}
+
+/// What the dependencies of a project resolved to.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Resolution {
+    /// The plugin roots of the dependencies.
+    pub roots: Vec<PathBuf>,
+    /// The lockfile that records the resolution.
+    pub lockfile: Lockfile,
+}
+
+/// Downloads dependencies into a cache.
+#[derive(Debug)]
+pub struct DependencyResolver {
+    cache_path: PathBuf,
+    /// The registry marketplace dependencies are resolved against.
+    registry: Option<Box<dyn Registry>>,
+}
+
+impl DependencyResolver {
+    /// Creates a resolver that caches dependencies in a folder.
+    pub fn new(cache_path: PathBuf) -> Self {
+        DependencyResolver {
+            cache_path,
+            registry: None,
+        }
+    }
+
+    /// Resolves marketplace dependencies against a registry.
+    pub fn with_registry(mut self, registry: impl Registry + 'static) -> Self {
+        self.registry = Some(Box::new(registry));
+        self
+    }
+
+    /// Creates a resolver that caches dependencies in the cache folder of the user.
+    ///
+    /// The folder can be chosen with the [`CACHE_VARIABLE`] environment variable. Returns `None`
+    /// if the user has no cache folder.
+    pub fn for_user() -> Option<Self> {
+        user_cache_path().map(Self::new)
+    }
+
+    /// Returns the folder dependencies are cached in.
+    pub fn cache_path(&self) -> &Path {
+        &self.cache_path
+    }
+
+    /// Downloads dependencies (unless they're already cached) and returns their plugin roots,
+    /// including the ones of the packages marketplace dependencies depend on.
+    ///
+    /// Locked versions are kept as long as they fit the dependencies, and locked files must not
+    /// have changed.
+    pub fn resolve<'d>(
+        &self,
+        dependencies: impl IntoIterator<Item = &'d ProjectDependency>,
+        locked: Option<&Lockfile>,
+    ) -> Result<Resolution, DependencyError> {
+        let mut resolution = Resolution::default();
+        let mut requirements = Vec::new();
+        for dependency in dependencies {
+            match dependency {
+                ProjectDependency::Git(dependency) => {
+                    let root = self.resolve_git(dependency)?;
+                    let checksum = content_hash(&root)?;
+                    let locked_checksum = locked.and_then(|locked| {
+                        locked.git(&dependency.repository_url, &dependency.commit_hash)
+                    });
+                    if locked_checksum.is_some_and(|locked| locked != checksum) {
+                        return Err(DependencyError::LockMismatch {
+                            package: dependency.repository_url.to_string(),
+                        });
+                    }
+                    resolution.roots.push(root);
+                    resolution.lockfile.packages.push(LockedPackage::Git {
+                        repository_url: dependency.repository_url.clone(),
+                        commit_hash: dependency.commit_hash.clone(),
+                        checksum,
+                    });
+                }
+                ProjectDependency::Marketplace(dependency) => requirements.push(dependency),
+            }
+        }
+        if !requirements.is_empty() {
+            for package in self.resolve_marketplace(&requirements, locked)? {
+                resolution.roots.push(package.root);
+                resolution
+                    .lockfile
+                    .packages
+                    .push(LockedPackage::Marketplace {
+                        marketplace_id: package.id,
+                        version: package.version,
+                        checksum: package.checksum,
+                    });
+            }
+        }
+        Ok(resolution)
+    }
+
+    /// Fetches what a lockfile records (unless it's already cached) and returns the plugin roots,
+    /// without looking at what the project requires.
+    ///
+    /// The registry is only needed for marketplace packages that aren't cached. Locked files
+    /// must not have changed.
+    pub fn resolve_locked(&self, locked: &Lockfile) -> Result<Vec<PathBuf>, DependencyError> {
+        locked
+            .packages
+            .iter()
+            .map(|package| match package {
+                LockedPackage::Git {
+                    repository_url,
+                    commit_hash,
+                    checksum,
+                } => {
+                    let dependency = GitDependency::new(repository_url.clone(), commit_hash);
+                    let root = self.resolve_git(&dependency)?;
+                    if content_hash(&root)? != *checksum {
+                        return Err(DependencyError::LockMismatch {
+                            package: repository_url.to_string(),
+                        });
+                    }
+                    Ok(root)
+                }
+                LockedPackage::Marketplace {
+                    marketplace_id,
+                    version,
+                    checksum,
+                } => {
+                    registry::check_id(marketplace_id)?;
+                    let root = registry::install_path(&self.cache_path, marketplace_id, version);
+                    if root.exists() && content_hash(&root)? == *checksum {
+                        return Ok(root);
+                    }
+                    let package = format!("{marketplace_id} {version}");
+                    let registry = self
+                        .registry
+                        .as_deref()
+                        .ok_or(DependencyError::NoRegistry)?;
+                    let entry = registry
+                        .package(marketplace_id)?
+                        .versions
+                        .into_iter()
+                        .find(|entry| entry.version == *version)
+                        .ok_or_else(|| DependencyError::PackageNotFound(package.clone()))?;
+                    if entry.checksum != *checksum {
+                        return Err(DependencyError::LockMismatch { package });
+                    }
+                    Ok(registry::install(&self.cache_path, registry, marketplace_id, &entry)?.root)
+                }
+            })
+            .collect()
+    }
+
+    /// Fetches a git dependency (unless it's already cached) and returns its plugin root.
+    pub fn resolve_git(&self, dependency: &GitDependency) -> Result<PathBuf, DependencyError> {
+        git::resolve(&self.cache_path, dependency)
+    }
+
+    /// Picks a version of each package that fits every requirement on it, and installs them
+    /// (unless they're already cached).
+    ///
+    /// The locked version of a package is picked if it fits, and the highest one otherwise.
+    pub fn resolve_marketplace(
+        &self,
+        requirements: &[&MarketplaceDependency],
+        locked: Option<&Lockfile>,
+    ) -> Result<Vec<ResolvedPackage>, DependencyError> {
+        let registry = self
+            .registry
+            .as_deref()
+            .ok_or(DependencyError::NoRegistry)?;
+        let locked_versions = locked
+            .into_iter()
+            .flat_map(|locked| &locked.packages)
+            .filter_map(|package| match package {
+                LockedPackage::Marketplace {
+                    marketplace_id,
+                    version,
+                    ..
+                } => Some((marketplace_id.clone(), version.clone())),
+                LockedPackage::Git { .. } => None,
+            })
+            .collect();
+
+        registry::resolve(registry, requirements, &locked_versions)?
+            .iter()
+            .map(|(id, entry)| {
+                let locked_checksum = locked
+                    .and_then(|locked| locked.marketplace(id))
+                    .filter(|(version, _)| **version == entry.version);
+                if locked_checksum.is_some_and(|(_, checksum)| checksum != entry.checksum) {
+                    return Err(DependencyError::LockMismatch {
+                        package: format!("{id} {}", entry.version),
+                    });
+                }
+                registry::install(&self.cache_path, registry, id, entry)
+            })
+            .collect()
+    }
+}
+
+/// Hashes the files in a folder (except for git's), along with their paths.
+///
+/// Two folders have the same hash only if they have the same files with the same contents.
+pub fn content_hash(path: &Path) -> Result<String, DependencyError> {
+    let mut listing = Vec::new();
+    let files = WalkDir::new(path)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| entry.file_name() != ".git");
+    for file in files {
+        let file = file.map_err(std::io::Error::from)?;
+        if !file.file_type().is_file() {
+            continue;
+        }
+        let relative = file.path().strip_prefix(path).unwrap_or(file.path());
+        let components = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>();
+        let oid = git2::Oid::hash_file(git2::ObjectType::Blob, file.path())?;
+        listing.extend_from_slice(format!("{oid} {}\n", components.join("/")).as_bytes());
+    }
+    Ok(git2::Oid::hash_object(git2::ObjectType::Blob, &listing)?.to_string())
+}
+
+/// Returns the folder the user's cached dependencies are in, if there's one.
+fn user_cache_path() -> Option<PathBuf> {
+    use std::env::var_os;
+
+    if let Some(path) = var_os(CACHE_VARIABLE) {
+        return Some(path.into());
+    }
+    let cache = if cfg!(windows) {
+        PathBuf::from(var_os("LOCALAPPDATA")?)
+    } else if let Some(cache) = var_os("XDG_CACHE_HOME") {
+        PathBuf::from(cache)
+    } else {
+        PathBuf::from(var_os("HOME")?).join(".cache")
+    };
+    Some(cache.join("rpg-baker"))
+}
@@ -0,0 +1,561 @@
+//! # Marketplace registries
+//!
+//! A registry is where marketplace packages are published. It has an index file for each
+//! package, listing the versions that were published, what each of them depends on and the
+//! [`content_hash`](super::content_hash) of its files:
+//!
+//! ```text
+//! index/<package id>.json
+//! ```
+//!
+//! ```json
+//! {
+//!   "id": "kit",
+//!   "versions": [
+//!     {
+//!       "version": "1.2.0",
+//!       "path": "packages/kit/1.2.0",
+//!       "checksum": "…",
+//!       "dependencies": [{ "marketplace_id": "dice", "version_requirement": "^0.3" }]
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! The index can be read from a folder ([`DirectoryRegistry`]) or served by anything else that
+//! implements [`Registry`], like an HTTP endpoint.
+//!
+//! Package ids and the paths of versions end up in paths on the user's disk, so they're checked
+//! before they're used: an id must be a plain file name, and a path must stay inside the registry.
+//!
+//! Requirements are resolved to the highest version that fits all of them. When no version of a
+//! package fits, the version of the package that required it last is left out, and resolution
+//! starts over; if the project itself requires versions that don't fit, that's a conflict.
+
+use super::{DependencyError, content_hash};
+use crate::{plugin::is_inside_folder, project::MarketplaceDependency};
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::{Component, Path, PathBuf},
+};
+use walkdir::WalkDir;
+
+/// What a project requires from a package, and who requires it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Requirement {
+    /// The package and version that depends on the package, or `None` for the project itself.
+    pub required_by: Option<(String, Version)>,
+    pub version_requirement: VersionReq,
+}
+
+impl std::fmt::Display for Requirement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.required_by {
+            Some((id, version)) => write!(
+                f,
+                "{} (required by {id} {version})",
+                self.version_requirement
+            ),
+            None => write!(f, "{} (required by the project)", self.version_requirement),
+        }
+    }
+}
+
+/// Every published version of a package.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackageIndex {
+    pub id: String,
+    pub versions: Vec<IndexEntry>,
+}
+
+/// A published version of a package.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexEntry {
+    pub version: Version,
+    /// Where the files of the version are, relative to the registry.
+    pub path: PathBuf,
+    /// The content hash of the files of the version.
+    pub checksum: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<MarketplaceDependency>,
+    /// Whether the version was taken down; yanked versions aren't picked anymore.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub yanked: bool,
+}
+
+/// A version of a package that was picked to fulfill the requirements of a project.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedPackage {
+    pub id: String,
+    pub version: Version,
+    pub checksum: String,
+    /// The folder the package was installed to.
+    pub root: PathBuf,
+}
+
+/// Where marketplace packages are published.
+pub trait Registry: std::fmt::Debug {
+    /// Returns the index of a package.
+    fn package(&self, id: &str) -> Result<PackageIndex, DependencyError>;
+
+    /// Copies the files of a version of a package into an empty folder.
+    fn download(&self, entry: &IndexEntry, destination: &Path) -> Result<(), DependencyError>;
+}
+
+/// A registry in a local folder (or a network share).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirectoryRegistry {
+    path: PathBuf,
+}
+
+impl DirectoryRegistry {
+    /// Opens the registry in a folder.
+    pub fn new(path: PathBuf) -> Self {
+        DirectoryRegistry { path }
+    }
+}
+
+impl Registry for DirectoryRegistry {
+    fn package(&self, id: &str) -> Result<PackageIndex, DependencyError> {
+        check_id(id)?;
+        let path = self.path.join("index").join(format!("{id}.json"));
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                return Err(DependencyError::PackageNotFound(id.to_string()));
+            }
+            Err(error) => return Err(error.into()),
+        };
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    fn download(&self, entry: &IndexEntry, destination: &Path) -> Result<(), DependencyError> {
+        check_path(&entry.path)?;
+        let source = self.path.join(&entry.path);
+        for file in WalkDir::new(&source).min_depth(1) {
+            let file = file.map_err(std::io::Error::from)?;
+            let target = destination.join(file.path().strip_prefix(&source).unwrap_or(file.path()));
+            if file.file_type().is_dir() {
+                std::fs::create_dir_all(target)?;
+            } else {
+                std::fs::copy(file.path(), target)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Picks a version of every package the requirements need, including what those packages
+/// depend on themselves.
+///
+/// Locked versions are picked whenever they fit, even if they were yanked. Returns the picked
+/// version of each package, by package id.
+pub fn resolve(
+    registry: &dyn Registry,
+    requirements: &[&MarketplaceDependency],
+    locked: &HashMap<String, Version>,
+) -> Result<BTreeMap<String, IndexEntry>, DependencyError> {
+    let mut indices = HashMap::<String, PackageIndex>::new();
+    let mut excluded = HashSet::<(String, Version)>::new();
+    // The first conflict is reported, as later ones only come from working around it.
+    let mut first_conflict = None;
+    loop {
+        match resolve_excluding(registry, &mut indices, requirements, locked, &excluded)? {
+            Ok(picked) => return Ok(picked),
+            Err(Conflict {
+                error,
+                culprit: Some(culprit),
+            }) => {
+                first_conflict.get_or_insert(error);
+                excluded.insert(culprit);
+            }
+            Err(Conflict { error, .. }) => return Err(first_conflict.unwrap_or(error)),
+        }
+    }
+}
+
+/// A package no version of which fits its requirements.
+struct Conflict {
+    error: DependencyError,
+    /// The version of a package that required it, which may not be needed.
+    culprit: Option<(String, Version)>,
+}
+
+/// Picks versions without using some of them.
+fn resolve_excluding(
+    registry: &dyn Registry,
+    indices: &mut HashMap<String, PackageIndex>,
+    requirements: &[&MarketplaceDependency],
+    locked: &HashMap<String, Version>,
+    excluded: &HashSet<(String, Version)>,
+) -> Result<Result<BTreeMap<String, IndexEntry>, Conflict>, DependencyError> {
+    let mut requirements_by_package = BTreeMap::<String, Vec<Requirement>>::new();
+    for requirement in requirements {
+        add_requirement(&mut requirements_by_package, requirement, None);
+    }
+
+    let mut picked = BTreeMap::<String, IndexEntry>::new();
+    loop {
+        let mut changed = false;
+        for (id, requirements) in &requirements_by_package {
+            if !indices.contains_key(id) {
+                indices.insert(id.clone(), registry.package(id)?);
+            }
+            let is_locked = |entry: &IndexEntry| locked.get(id) == Some(&entry.version);
+            let candidates = indices[id]
+                .versions
+                .iter()
+                .filter(|entry| !entry.yanked || is_locked(entry))
+                .filter(|entry| !excluded.contains(&(id.clone(), entry.version.clone())))
+                .filter(|entry| {
+                    requirements
+                        .iter()
+                        .all(|requirement| requirement.version_requirement.matches(&entry.version))
+                });
+            let best = match candidates.clone().find(|entry| is_locked(entry)) {
+                Some(entry) => Some(entry),
+                None => candidates.max_by(|a, b| a.version.cmp(&b.version)),
+            };
+            let Some(best) = best else {
+                // Another version of the package that asked for this one last may not need it.
+                return Ok(Err(Conflict {
+                    error: DependencyError::Conflict {
+                        package: id.clone(),
+                        requirements: requirements.clone(),
+                    },
+                    culprit: requirements
+                        .iter()
+                        .rev()
+                        .find_map(|r| r.required_by.clone()),
+                }));
+            };
+            if picked
+                .get(id)
+                .is_none_or(|entry| entry.version != best.version)
+            {
+                picked.insert(id.clone(), best.clone());
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+
+        // Requirements only get added, so a package is only picked again when its version stops
+        // fitting, and then the highest version that fits only gets lower.
+        for (id, entry) in &picked {
+            let required_by = (id.clone(), entry.version.clone());
+            for dependency in &entry.dependencies {
+                add_requirement(
+                    &mut requirements_by_package,
+                    dependency,
+                    Some(required_by.clone()),
+                );
+            }
+        }
+    }
+
+    // Packages that were only needed by versions that weren't picked in the end are left out.
+    let mut needed = BTreeMap::new();
+    let mut pending = requirements
+        .iter()
+        .map(|requirement| requirement.marketplace_id.clone())
+        .collect::<Vec<_>>();
+    while let Some(id) = pending.pop() {
+        if needed.contains_key(&id) {
+            continue;
+        }
+        if let Some(entry) = picked.remove(&id) {
+            pending.extend(entry.dependencies.iter().map(|d| d.marketplace_id.clone()));
+            needed.insert(id, entry);
+        }
+    }
+    Ok(Ok(needed))
+}
+
+fn add_requirement(
+    requirements_by_package: &mut BTreeMap<String, Vec<Requirement>>,
+    dependency: &MarketplaceDependency,
+    required_by: Option<(String, Version)>,
+) {
+    let requirement = Requirement {
+        required_by,
+        version_requirement: dependency.version_requirement.clone(),
+    };
+    let requirements = requirements_by_package
+        .entry(dependency.marketplace_id.clone())
+        .or_default();
+    if !requirements.contains(&requirement) {
+        requirements.push(requirement);
+    }
+}
+
+/// Installs a version of a package into a cache (unless it's already there), checking that its
+/// files are the ones that were published.
+pub fn install(
+    cache_path: &Path,
+    registry: &dyn Registry,
+    id: &str,
+    entry: &IndexEntry,
+) -> Result<ResolvedPackage, DependencyError> {
+    check_id(id)?;
+    check_path(&entry.path)?;
+    let root = install_path(cache_path, id, &entry.version);
+    let installed = root.exists() && content_hash(&root)? == entry.checksum;
+    if !installed {
+        if root.exists() {
+            std::fs::remove_dir_all(&root)?;
+        }
+        std::fs::create_dir_all(&root)?;
+        registry.download(entry, &root)?;
+
+        if content_hash(&root)? != entry.checksum {
+            std::fs::remove_dir_all(&root)?;
+            return Err(DependencyError::ChecksumMismatch {
+                package: id.to_string(),
+                version: entry.version.clone(),
+            });
+        }
+    }
+
+    Ok(ResolvedPackage {
+        id: id.to_string(),
+        version: entry.version.clone(),
+        checksum: entry.checksum.clone(),
+        root,
+    })
+}
+
+/// Returns the folder a version of a package is installed to.
+pub fn install_path(cache_path: &Path, id: &str, version: &Version) -> PathBuf {
+    cache_path
+        .join("registry")
+        .join(id)
+        .join(version.to_string())
+}
+
+/// Checks that a package id is a plain file name, so it can name folders.
+pub(super) fn check_id(id: &str) -> Result<(), DependencyError> {
+    let mut components = Path::new(id).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(name)), None) if name == id => Ok(()),
+        _ => Err(DependencyError::InvalidPackageId(id.to_string())),
+    }
+}
+
+/// Checks that the path of a version of a package is a folder inside the registry.
+fn check_path(path: &Path) -> Result<(), DependencyError> {
+    if is_inside_folder(path) {
+        Ok(())
+    } else {
+        Err(DependencyError::InvalidPackagePath(path.to_path_buf()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Makes a registry with a package whose only version is at `path`, and returns the registry
+    /// with the entry of the version.
+    fn registry_with_package(registry_path: &Path, path: &str) -> (DirectoryRegistry, IndexEntry) {
+        let files = registry_path.join("packages").join("dice");
+        fs::create_dir_all(&files).unwrap();
+        fs::write(files.join("plugin.json"), "{}").unwrap();
+        let entry = IndexEntry {
+            version: Version::new(1, 0, 0),
+            path: PathBuf::from(path),
+            checksum: content_hash(&files).unwrap(),
+            dependencies: Vec::new(),
+            yanked: false,
+        };
+        (DirectoryRegistry::new(registry_path.to_path_buf()), entry)
+    }
+
+    /// A registry that only has indices, for resolving versions.
+    #[derive(Debug, Default)]
+    struct IndexRegistry(HashMap<String, PackageIndex>);
+
+    impl IndexRegistry {
+        /// Publishes a version of a package, depending on some packages like `("dice", "^2")`.
+        fn publish(mut self, id: &str, version: &str, dependencies: &[(&str, &str)]) -> Self {
+            let entry = IndexEntry {
+                version: version.parse().unwrap(),
+                path: PathBuf::from(id),
+                checksum: String::new(),
+                dependencies: dependencies
+                    .iter()
+                    .map(|(id, requirement)| {
+                        MarketplaceDependency::new(*id, requirement.parse().unwrap())
+                    })
+                    .collect(),
+                yanked: false,
+            };
+            self.0
+                .entry(id.to_string())
+                .or_insert_with(|| PackageIndex {
+                    id: id.to_string(),
+                    versions: Vec::new(),
+                })
+                .versions
+                .push(entry);
+            self
+        }
+    }
+
+    impl Registry for IndexRegistry {
+        fn package(&self, id: &str) -> Result<PackageIndex, DependencyError> {
+            self.0
+                .get(id)
+                .cloned()
+                .ok_or_else(|| DependencyError::PackageNotFound(id.to_string()))
+        }
+
+        fn download(&self, _: &IndexEntry, _: &Path) -> Result<(), DependencyError> {
+            unreachable!("resolving never downloads anything")
+        }
+    }
+
+    /// Resolves some requirements like `("dice", "^2")`, returning the picked versions.
+    fn picked(
+        registry: &IndexRegistry,
+        requirements: &[(&str, &str)],
+        locked: &[(&str, &str)],
+    ) -> Result<Vec<(String, String)>, DependencyError> {
+        let requirements = requirements
+            .iter()
+            .map(|(id, requirement)| MarketplaceDependency::new(*id, requirement.parse().unwrap()))
+            .collect::<Vec<_>>();
+        let locked = locked
+            .iter()
+            .map(|(id, version)| (id.to_string(), version.parse().unwrap()))
+            .collect();
+        let picked = resolve(registry, &requirements.iter().collect::<Vec<_>>(), &locked)?;
+        Ok(picked
+            .into_iter()
+            .map(|(id, entry)| (id, entry.version.to_string()))
+            .collect())
+    }
+
+    fn versions(picked: &[(&str, &str)]) -> Vec<(String, String)> {
+        picked
+            .iter()
+            .map(|(id, version)| (id.to_string(), version.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn resolves_what_packages_depend_on() {
+        let registry = IndexRegistry::default()
+            .publish("app", "1.0.0", &[("dice", "^2")])
+            .publish("app", "1.1.0", &[("dice", "^3")])
+            .publish("dice", "2.0.0", &[])
+            .publish("dice", "2.1.0", &[])
+            .publish("dice", "3.0.0", &[]);
+
+        assert_eq!(
+            picked(&registry, &[("app", "^1")], &[]).unwrap(),
+            versions(&[("app", "1.1.0"), ("dice", "3.0.0")])
+        );
+        // The newest `app` needs a `dice` the project doesn't accept, so an older one is picked.
+        assert_eq!(
+            picked(&registry, &[("app", "^1"), ("dice", "^2")], &[]).unwrap(),
+            versions(&[("app", "1.0.0"), ("dice", "2.1.0")])
+        );
+    }
+
+    #[test]
+    fn reports_conflicts() {
+        let registry = IndexRegistry::default()
+            .publish("app", "1.0.0", &[("dice", "^3")])
+            .publish("dice", "2.0.0", &[])
+            .publish("dice", "3.0.0", &[]);
+
+        match picked(&registry, &[("app", "^1"), ("dice", "^2")], &[]) {
+            Err(DependencyError::Conflict {
+                package,
+                requirements,
+            }) => {
+                assert_eq!(package, "dice");
+                assert_eq!(requirements.len(), 2);
+            }
+            other => panic!("expected a conflict, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn keeps_locked_versions_while_they_fit() {
+        let registry = IndexRegistry::default()
+            .publish("dice", "2.0.0", &[])
+            .publish("dice", "2.1.0", &[])
+            .publish("dice", "3.0.0", &[]);
+
+        assert_eq!(
+            picked(&registry, &[("dice", "^2")], &[("dice", "2.0.0")]).unwrap(),
+            versions(&[("dice", "2.0.0")])
+        );
+        // The project now asks for a version the locked one doesn't fit.
+        assert_eq!(
+            picked(&registry, &[("dice", "^3")], &[("dice", "2.0.0")]).unwrap(),
+            versions(&[("dice", "3.0.0")])
+        );
+    }
+
+    #[test]
+    fn installs_packages() {
+        let temp = tempfile::tempdir().unwrap();
+        let (registry, entry) =
+            registry_with_package(&temp.path().join("registry"), "packages/dice");
+        let cache = temp.path().join("cache");
+
+        let package = install(&cache, &registry, "dice", &entry).unwrap();
+        assert_eq!(package.root, install_path(&cache, "dice", &entry.version));
+        assert!(package.root.join("plugin.json").is_file());
+    }
+
+    #[test]
+    fn rejects_ids_that_are_not_file_names() {
+        let temp = tempfile::tempdir().unwrap();
+        let (registry, entry) =
+            registry_with_package(&temp.path().join("registry"), "packages/dice");
+        let cache = temp.path().join("cache");
+        // What an id like `../../victim` would point to from the cache.
+        let victim = temp.path().join("victim").join("1.0.0");
+        fs::create_dir_all(&victim).unwrap();
+
+        for id in ["../../victim", "a/b", "/etc", "..", "."] {
+            assert!(matches!(
+                registry.package(id),
+                Err(DependencyError::InvalidPackageId(_))
+            ));
+            assert!(matches!(
+                install(&cache, &registry, id, &entry),
+                Err(DependencyError::InvalidPackageId(_))
+            ));
+        }
+        assert!(victim.exists());
+    }
+
+    #[test]
+    fn rejects_paths_outside_of_the_registry() {
+        let temp = tempfile::tempdir().unwrap();
+        let registry_path = temp.path().join("registry");
+        let cache = temp.path().join("cache");
+        fs::create_dir_all(temp.path().join("secrets")).unwrap();
+
+        for path in ["../secrets", "packages/../../secrets", "/etc", ""] {
+            let (registry, entry) = registry_with_package(&registry_path, path);
+            assert!(matches!(
+                install(&cache, &registry, "dice", &entry),
+                Err(DependencyError::InvalidPackagePath(_))
+            ));
+            assert!(matches!(
+                registry.download(&entry, &temp.path().join("destination")),
+                Err(DependencyError::InvalidPackagePath(_))
+            ));
+        }
+    }
+}
@@ -1,20 +1,33 @@
+pub mod dependency;
 pub mod object;
 pub mod resource;
 pub mod room;
 pub mod runtime;
-use ordermap::OrderSet;
-use resource::{ResourceDatabase, ResourceLoadError, ResourceSaveError};
-use runtime::Game;
+use dependency::{
+    DependencyError, DependencyResolver,
+    lock::{LOCK_FILE, Lockfile},
+};
+use ordermap::{OrderMap, OrderSet};
+use resource::{
+    ResourceDatabase, ResourceLoadError, ResourceSaveError, ResourceWatcher, references,
+};
+use runtime::{Game, GameError};
 use semver::{Version, VersionReq};
 use serde::{Deserialize, Serialize};
 use serde_json::{from_str, to_string_pretty};
-use std::path::PathBuf;
+use std::{collections::HashSet, path::PathBuf};
 use url::Url;
+use uuid::Uuid;
 
 use crate::{
-    behaviour::BehaviourDescriptor,
+    behaviour::{
+        BehaviourDescriptor,
+        check::TypeChecker,
+        registry::BlockRegistry,
+        variables::{VariableScope, Variables},
+    },
     format::{BaseType, Format},
-    plugin::PluginDatabase,
+    plugin::{PluginDatabase, PluginState},
 };
 
 /// An RPG Baker project, assumed to be saved on disk to a folder.
@@ -33,8 +46,14 @@ pub struct Project {
 
     /* Plugins and Dependencies */
     dependencies: OrderSet<ProjectDependency>,
+    /// How the project uses each of its plugins, by plugin id.
+    #[serde(default, skip_serializing_if = "OrderMap::is_empty")]
+    plugins: OrderMap<String, PluginState>,
     #[serde(skip)]
     pub plugin_database: PluginDatabase,
+    /// Why the dependencies recorded in the lockfile couldn't be loaded, if they couldn't.
+    #[serde(skip)]
+    dependency_error: Option<DependencyError>,
 
     /* Game Stuff */
     story_definition: Format,
@@ -52,25 +71,208 @@ impl Project {
             base_path: path.clone(),
             resource_database: ResourceDatabase::default(),
             plugin_database: PluginDatabase::default(),
+            dependency_error: None,
             story_definition: Format::BaseType(BaseType::Void),
             startup_behaviour: BehaviourDescriptor::new(),
             dependencies: OrderSet::new(),
+            plugins: OrderMap::new(),
         };
 
-        project._save_as(path)?;
+        project._save_as(path.clone())?;
+        // New resources are created inside of the project's folder.
+        project.resource_database =
+            ResourceDatabase::from_directory_excluding(path, &[project.plugins_path()]);
 
         Ok(project)
     }
 
     /// Loads a project from a directory containing a `project.json` file.
+    ///
+    /// The plugins of the dependencies recorded in the project's lockfile are loaded from the
+    /// cache folder of the user (see [`DependencyResolver::for_user`]).
     pub fn load(path: PathBuf) -> Result<Self, ResourceLoadError> {
+        Self::load_with(path, DependencyResolver::for_user().as_ref())
+    }
+
+    /// Loads a project from a directory containing a `project.json` file, loading the plugins of
+    /// the dependencies recorded in its lockfile with a resolver.
+    ///
+    /// Locked dependencies that fail to load don't stop the project from loading; see
+    /// [`Project::dependency_error`].
+    pub fn load_with(
+        path: PathBuf,
+        resolver: Option<&DependencyResolver>,
+    ) -> Result<Self, ResourceLoadError> {
         let file = std::fs::read_to_string(path.join("project.json").as_path())?;
         let mut project: Project = from_str(file.as_str())?;
         project.base_path = path.clone();
-        project.resource_database = ResourceDatabase::from_directory(path);
+        // Plugins have resources of their own, which the project can't change.
+        project.resource_database =
+            ResourceDatabase::from_directory_excluding(path, &[project.plugins_path()]);
+        project.plugin_database =
+            PluginDatabase::from_directories([project.plugins_path()], &project.plugins);
+        for plugin in &project.plugin_database.plugins {
+            project.resource_database.add_plugin(plugin);
+        }
+
+        let roots =
+            Lockfile::load(project.lock_path()).and_then(|locked| match (locked, resolver) {
+                (None, _) => Ok(Vec::new()),
+                (Some(_), None) => Err(DependencyError::NoCache),
+                (Some(locked), Some(resolver)) => resolver.resolve_locked(&locked),
+            });
+        match roots {
+            Ok(roots) => project.add_dependency_roots(roots),
+            Err(error) => project.dependency_error = Some(error),
+        }
         Ok(project)
     }
 
+    /// Watches over the project's folder from a background thread, hot reloading its resources,
+    /// until the returned watcher is dropped.
+    pub fn watch_resources(&self) -> Result<ResourceWatcher, notify::Error> {
+        self.resource_database.watch(self.base_path.clone())
+    }
+
+    /// Returns the resources the project itself refers to, like the blocks of its startup
+    /// behaviour. Any other resource the game uses is reached from them, so they're the roots
+    /// of [`ReferenceGraph::unreachable`](resource::ReferenceGraph::unreachable).
+    pub fn root_resources(&self) -> HashSet<Uuid> {
+        let mut roots = HashSet::new();
+        if let Ok(value) = serde_json::to_value(&self.story_definition) {
+            roots.extend(references(&value));
+        }
+        if let Ok(value) = serde_json::to_value(&self.startup_behaviour) {
+            roots.extend(references(&value));
+        }
+        roots
+    }
+
+    /// Returns the global variables declared by the project's story definition, holding the
+    /// defaults of their formats.
+    pub fn story_variables(&self) -> Variables {
+        match &self.story_definition {
+            Format::Tuple(fields) => Variables::from_declarations(
+                fields.iter().map(|(name, format)| (name.as_str(), format)),
+            ),
+            _ => Variables::default(),
+        }
+    }
+
+    /// Returns a checker that knows the project's story variables, and the blocks of its active
+    /// plugins and custom blocks.
+    ///
+    /// This loads every resource of the project.
+    pub fn type_checker(&self) -> TypeChecker {
+        TypeChecker::new()
+            .with_variables(VariableScope::Global, self.story_variables())
+            .with_registry(
+                BlockRegistry::new(&self.plugin_database)
+                    .with_custom_blocks(&self.resource_database),
+            )
+    }
+
+    /// Returns the folder the project's own plugins are in.
+    pub fn plugins_path(&self) -> PathBuf {
+        self.base_path.join("plugins")
+    }
+
+    /// Activates or deactivates a plugin, remembering it in the project.
+    ///
+    /// The resources of the plugin are merged into the project's, or taken out of it.
+    ///
+    /// Returns whether the plugin is installed.
+    pub fn set_plugin_active(&mut self, plugin_id: &str, active: bool) -> bool {
+        if !self.plugin_database.set_active(plugin_id, active) {
+            return false;
+        }
+        match self.plugin_database.get(plugin_id) {
+            Some(plugin) if active => self.resource_database.add_plugin(plugin),
+            _ => self.resource_database.remove_plugin(plugin_id),
+        }
+        self.plugins
+            .insert(plugin_id.to_string(), PluginState { active });
+        true
+    }
+
+    /// Returns the dependencies of the project.
+    pub fn dependencies(&self) -> &OrderSet<ProjectDependency> {
+        &self.dependencies
+    }
+
+    /// Adds a dependency to the project, returning whether it wasn't there already.
+    ///
+    /// The dependency is only used once it's resolved.
+    pub fn add_dependency(&mut self, dependency: ProjectDependency) -> bool {
+        self.dependencies.insert(dependency)
+    }
+
+    /// Returns why the dependencies recorded in the lockfile couldn't be loaded along with the
+    /// project, if they couldn't. Resolving the dependencies again clears it.
+    pub fn dependency_error(&self) -> Option<&DependencyError> {
+        self.dependency_error.as_ref()
+    }
+
+    /// Returns the path of the project's lockfile.
+    pub fn lock_path(&self) -> PathBuf {
+        self.base_path.join(LOCK_FILE)
+    }
+
+    /// Downloads the dependencies of the project (unless they're already cached) and loads the
+    /// plugins they provide, along with their resources.
+    ///
+    /// The versions in the project's lockfile are used as long as they fit the dependencies;
+    /// the lockfile is then updated with what the dependencies resolved to.
+    pub fn resolve_dependencies(
+        &mut self,
+        resolver: &DependencyResolver,
+    ) -> Result<(), DependencyError> {
+        let locked = Lockfile::load(self.lock_path())?;
+        self.resolve_dependencies_with(resolver, locked.as_ref())
+    }
+
+    /// Like [`Project::resolve_dependencies`], but ignores the lockfile, so that dependencies
+    /// resolve to their newest versions.
+    pub fn update_dependencies(
+        &mut self,
+        resolver: &DependencyResolver,
+    ) -> Result<(), DependencyError> {
+        self.resolve_dependencies_with(resolver, None)
+    }
+
+    fn resolve_dependencies_with(
+        &mut self,
+        resolver: &DependencyResolver,
+        locked: Option<&Lockfile>,
+    ) -> Result<(), DependencyError> {
+        let resolution = resolver.resolve(&self.dependencies, locked)?;
+        if locked != Some(&resolution.lockfile) {
+            resolution.lockfile.save(self.lock_path())?;
+        }
+        self.add_dependency_roots(resolution.roots);
+        self.dependency_error = None;
+        Ok(())
+    }
+
+    /// Loads the plugins of resolved dependencies, along with their resources.
+    ///
+    /// A dependency that resolved to another folder than before (like another version) replaces
+    /// the plugin it provided before, and its resources.
+    fn add_dependency_roots(&mut self, roots: Vec<PathBuf>) {
+        let plugins_path = self.plugins_path();
+        for root in roots {
+            let plugin = self
+                .plugin_database
+                .replace_plugin_root(root, &self.plugins, |plugin| {
+                    // The project's own plugins aren't dependencies.
+                    !plugin.path().starts_with(&plugins_path)
+                });
+            if let Some(plugin) = plugin {
+                self.resource_database.add_plugin(plugin);
+            }
+        }
+    }
+
     /// Saves a project to a directory (this changes the saved path of the project).
     #[inline]
     pub fn save_as(&mut self, path: PathBuf) -> Result<(), ResourceSaveError> {
@@ -94,10 +296,10 @@ impl Project {
 
     /// Runs a game baked from this project from the "beggining," that is,
     /// it runs an empty game then calls the `game_started` event.
-    pub fn run_from_start(&self) -> Game {
+    pub fn run_from_start(&self) -> Result<Game<'_>, GameError> {
         let mut game = Game::from_project(self);
-        game.game_started();
-        game
+        game.game_started()?;
+        Ok(game)
     }
 }
 
@@ -114,8 +316,48 @@ pub struct MarketplaceDependency {
     version_requirement: VersionReq,
 }
 
+impl MarketplaceDependency {
+    /// Creates a dependency on the versions of a marketplace package that fit a requirement.
+    pub fn new(marketplace_id: impl Into<String>, version_requirement: VersionReq) -> Self {
+        MarketplaceDependency {
+            marketplace_id: marketplace_id.into(),
+            version_requirement,
+        }
+    }
+
+    /// Returns the id of the package in the marketplace.
+    pub fn marketplace_id(&self) -> &str {
+        &self.marketplace_id
+    }
+
+    /// Returns the versions of the package that can be used.
+    pub fn version_requirement(&self) -> &VersionReq {
+        &self.version_requirement
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
 pub struct GitDependency {
     repository_url: Url,
     commit_hash: String,
 }
+
+impl GitDependency {
+    /// Creates a dependency on a commit of a git repository, given by its full hash.
+    pub fn new(repository_url: Url, commit_hash: impl Into<String>) -> Self {
+        GitDependency {
+            repository_url,
+            commit_hash: commit_hash.into(),
+        }
+    }
+
+    /// Returns the URL of the repository.
+    pub fn repository_url(&self) -> &Url {
+        &self.repository_url
+    }
+
+    /// Returns the hash of the commit the dependency is pinned to.
+    pub fn commit_hash(&self) -> &str {
+        &self.commit_hash
+    }
+}
@@ -1,5 +1,10 @@
-use crate::behaviour::BehaviourDescriptor;
+use super::resource::ExternalResource;
+use crate::behaviour::{
+    BehaviourDescriptor, BehaviourInstance, ReifyContext, ReifyError,
+    variables::{VariableDeclaration, Variables},
+};
 use serde::{Deserialize, Serialize};
+use std::fmt;
 
 /// An object's recipe, it describes how an object will be
 /// created when it's loaded in game.
@@ -8,9 +13,35 @@ pub struct ObjectRecipe {
     name: String,
     description: Option<String>,
     parts: Vec<Part>,
+    /// The variables each object baked from this recipe will have.
+    #[serde(default)]
+    variables: Vec<VariableDeclaration>,
     script: BehaviourDescriptor,
 }
 
+impl ObjectRecipe {
+    /// Creates a recipe for objects without parts, variables or behaviour.
+    pub fn new(name: String) -> Self {
+        ObjectRecipe {
+            name,
+            description: None,
+            parts: Vec::new(),
+            variables: Vec::new(),
+            script: BehaviourDescriptor::new(),
+        }
+    }
+
+    /// Returns the variables each object baked from this recipe will have.
+    pub fn variables(&self) -> &[VariableDeclaration] {
+        &self.variables
+    }
+
+    /// Returns the behaviour each object baked from this recipe will run.
+    pub fn script(&self) -> &BehaviourDescriptor {
+        &self.script
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Part {}
 
@@ -19,11 +50,64 @@ pub struct Part {}
 pub struct ObjectInstanceDescriptor {
     name: String,
     description: Option<String>,
+    /// The recipe the object is baked from.
+    recipe: ExternalResource,
+}
+
+impl ObjectInstanceDescriptor {
+    /// Describes an object baked from a recipe.
+    pub fn new(name: String, recipe: ExternalResource) -> Self {
+        ObjectInstanceDescriptor {
+            name,
+            description: None,
+            recipe,
+        }
+    }
+
+    /// Returns the recipe the object is baked from.
+    pub fn recipe(&self) -> &ExternalResource {
+        &self.recipe
+    }
 }
 
-/// An actual runtime object, containing references to the descriptor
+/// An actual runtime object, containing the descriptor
 /// that created it plus runtime data.
-#[derive(Debug, Clone, Serialize)]
+#[derive(Serialize)]
 pub struct ObjectInstance<'game> {
-    descriptor: &'game ObjectInstanceDescriptor,
+    descriptor: ObjectInstanceDescriptor,
+    /// The state of this object, as declared by its recipe.
+    pub variables: Variables,
+    /// The behaviour of this object, as given by its recipe.
+    #[serde(skip)]
+    pub behaviour: Option<BehaviourInstance<'game>>,
+}
+
+impl ObjectInstance<'_> {
+    /// Bakes an object from its recipe: its variables start out as the defaults of the formats
+    /// the recipe declares them with, and it runs the recipe's behaviour.
+    pub fn new(
+        descriptor: ObjectInstanceDescriptor,
+        recipe: &ObjectRecipe,
+        ctx: &ReifyContext,
+    ) -> Result<Self, ReifyError<'static>> {
+        Ok(ObjectInstance {
+            descriptor,
+            variables: Variables::from(recipe.variables.as_slice()),
+            behaviour: Some(recipe.script.clone().into_instance(ctx)?),
+        })
+    }
+
+    /// Returns the descriptor this object was baked from.
+    pub fn descriptor(&self) -> &ObjectInstanceDescriptor {
+        &self.descriptor
+    }
+}
+
+impl fmt::Debug for ObjectInstance<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ObjectInstance")
+            .field("descriptor", &self.descriptor)
+            .field("variables", &self.variables)
+            .finish_non_exhaustive()
+    }
 }
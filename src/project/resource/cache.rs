@@ -0,0 +1,277 @@
+//! Unloading resources that are no longer in use.
+//!
+//! Loaded resources stay cached after the last [`Handle`] to them is dropped, so that resources
+//! that are used on and off aren't read again every time. Once the loaded resources weigh more
+//! than the memory budget, the unused ones are unloaded, least recently used first, until they
+//! fit again. Resources that still have handles are never unloaded.
+//!
+//! Dropping a handle doesn't unload anything by itself: the budget is only enforced when a
+//! resource loads and when the budget is set. Resources can be unloaded at any other time (like
+//! when a room is left) with [`ResourceDatabase::unload_unused`].
+//!
+//! A resource weighs as much as its file, which is a rough (but cheap) measure of how much
+//! memory it takes.
+
+use super::{DatabaseState, Handle, Resource, ResourceDatabase, ResourceEntry};
+use std::{
+    path::Path,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+/// How much loaded resources may weigh by default before unused ones are unloaded, in bytes.
+pub const DEFAULT_MEMORY_BUDGET: u64 = 64 * 1024 * 1024;
+
+/// How many resources a database has loaded, and how much they weigh.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResourceStats {
+    /// How many resources are loaded.
+    pub loaded: usize,
+    /// How many of the loaded resources still have handles to them.
+    pub in_use: usize,
+    /// How much the loaded resources weigh, in bytes.
+    pub weight: u64,
+    /// How much loaded resources may weigh before unused ones are unloaded, in bytes.
+    pub memory_budget: u64,
+}
+
+/// How much a loaded resource weighs, and when it was last used.
+#[derive(Debug, Default)]
+pub(super) struct Usage {
+    weight: AtomicU64,
+    last_used: AtomicU64,
+}
+
+impl Usage {
+    /// Weighs a resource as its file.
+    pub(super) fn weigh(&self, path: &Path) {
+        let weight = std::fs::metadata(path).map_or(0, |metadata| metadata.len());
+        self.weight.store(weight, Ordering::Relaxed);
+    }
+}
+
+impl ResourceDatabase {
+    /// Returns how much loaded resources may weigh before unused ones are unloaded, in bytes.
+    pub fn memory_budget(&self) -> u64 {
+        self.state().memory_budget
+    }
+
+    /// Sets how much loaded resources may weigh before unused ones are unloaded, in bytes, and
+    /// unloads resources until they fit.
+    ///
+    /// With a budget of 0, resources are unloaded as soon as they're unused (the next time a
+    /// resource loads).
+    pub fn set_memory_budget(&self, memory_budget: u64) {
+        self.state_mut().memory_budget = memory_budget;
+        self.trim();
+    }
+
+    /// Returns how many resources are loaded, and how much they weigh.
+    pub fn stats(&self) -> ResourceStats {
+        stats_of(&self.state())
+    }
+
+    /// Unloads every resource that no handles are left to, whatever the budget, and returns
+    /// how many were unloaded.
+    pub fn unload_unused(&self) -> usize {
+        let mut unloaded = 0;
+        loop {
+            let state = self.state();
+            // Unloading a resource drops the handles it had to other resources, which may leave
+            // them unused in turn.
+            let unloaded_now = state
+                .resources
+                .values()
+                .filter(|entry| unload_if_unused(entry))
+                .count();
+            if unloaded_now == 0 {
+                return unloaded;
+            }
+            unloaded += unloaded_now;
+        }
+    }
+
+    /// Marks a resource as just used.
+    pub(super) fn touch(&self, entry: &ResourceEntry) {
+        let now = self.state().clock.fetch_add(1, Ordering::Relaxed) + 1;
+        entry.usage.last_used.store(now, Ordering::Relaxed);
+    }
+
+    /// Records that a resource was loaded, and unloads others if they don't fit in the budget
+    /// anymore.
+    pub(super) fn record_load(&self, entry: &ResourceEntry) {
+        entry.usage.weigh(&entry.path);
+        self.touch(entry);
+        self.trim();
+    }
+
+    /// Unloads unused resources, least recently used first, until the loaded ones fit in the
+    /// budget.
+    fn trim(&self) {
+        let state = self.state();
+        let mut weight = stats_of(&state).weight;
+        if weight <= state.memory_budget {
+            return;
+        }
+
+        let mut unused = state
+            .resources
+            .values()
+            .filter(|entry| {
+                let data = entry.data.lock_ref();
+                data.as_ref().is_some_and(|handle| handle.count() == 1)
+            })
+            .collect::<Vec<_>>();
+        unused.sort_by_key(|entry| entry.usage.last_used.load(Ordering::Relaxed));
+        for entry in unused {
+            if weight <= state.memory_budget {
+                break;
+            }
+            if unload_if_unused(entry) {
+                weight = weight.saturating_sub(entry.usage.weight.load(Ordering::Relaxed));
+            }
+        }
+    }
+}
+
+/// Counts the loaded resources of a database.
+fn stats_of(state: &DatabaseState) -> ResourceStats {
+    let mut stats = ResourceStats {
+        memory_budget: state.memory_budget,
+        ..Default::default()
+    };
+    for entry in state.resources.values() {
+        let data = entry.data.lock_ref();
+        let Some(handle) = &*data else {
+            continue;
+        };
+        stats.loaded += 1;
+        stats.weight += entry.usage.weight.load(Ordering::Relaxed);
+        if handle.count() > 1 {
+            stats.in_use += 1;
+        }
+    }
+    stats
+}
+
+/// Unloads a resource if it's loaded and no handles are left to it (besides the database's).
+fn unload_if_unused(entry: &ResourceEntry) -> bool {
+    let mut data = entry.data.lock_mut();
+    let is_unused = data
+        .as_ref()
+        .is_some_and(|handle: &Handle<Resource>| handle.count() == 1);
+    if is_unused {
+        *data = None;
+    }
+    is_unused
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::{Format, FormatDefinition};
+    use crate::project::resource::{ExternalResource, ResourceData};
+    use serde_json::json;
+    use uuid::Uuid;
+
+    /// Makes a database with a format file for each name, and returns it with their UUIDs and
+    /// how much each of them weighs.
+    fn database_of(temp: &Path, names: &[&str]) -> (ResourceDatabase, Vec<Uuid>, u64) {
+        let mut uuids = Vec::new();
+        let mut weight = 0;
+        for name in names {
+            let uuid = Uuid::new_v4();
+            let format = json!({
+                "uuid": uuid,
+                "type": "Format",
+                "data": { "name": name, "type": "tuple", "content": [] }
+            });
+            let path = temp.join(format!("{name}.json"));
+            std::fs::write(&path, format.to_string()).unwrap();
+            weight = std::fs::metadata(&path).unwrap().len();
+            uuids.push(uuid);
+        }
+        let database = ResourceDatabase::from_directory(temp.to_path_buf());
+        (database, uuids, weight)
+    }
+
+    fn is_loaded(database: &ResourceDatabase, uuid: &Uuid) -> bool {
+        database.state().resources[uuid].data.lock_ref().is_some()
+    }
+
+    #[test]
+    fn unloads_the_least_recently_used_resources_over_budget() {
+        let temp = tempfile::tempdir().unwrap();
+        let (database, uuids, weight) = database_of(temp.path(), &["a", "b", "c"]);
+        database.set_memory_budget(2 * weight);
+
+        database.get(&uuids[0]).unwrap();
+        database.get(&uuids[1]).unwrap();
+        // `a` was used after `b`, so `b` is the one that makes room for `c`.
+        database.get(&uuids[0]).unwrap();
+        database.get(&uuids[2]).unwrap();
+
+        assert!(is_loaded(&database, &uuids[0]));
+        assert!(!is_loaded(&database, &uuids[1]));
+        assert!(is_loaded(&database, &uuids[2]));
+        assert_eq!(
+            database.stats(),
+            ResourceStats {
+                loaded: 2,
+                in_use: 0,
+                weight: 2 * weight,
+                memory_budget: 2 * weight,
+            }
+        );
+    }
+
+    #[test]
+    fn never_unloads_resources_with_handles() {
+        let temp = tempfile::tempdir().unwrap();
+        let (database, uuids, weight) = database_of(temp.path(), &["a", "b"]);
+        let a = database.get(&uuids[0]).unwrap();
+        database.get(&uuids[1]).unwrap();
+
+        database.set_memory_budget(0);
+        assert!(is_loaded(&database, &uuids[0]));
+        assert!(!is_loaded(&database, &uuids[1]));
+        assert_eq!(
+            database.stats(),
+            ResourceStats {
+                loaded: 1,
+                in_use: 1,
+                weight,
+                memory_budget: 0,
+            }
+        );
+
+        drop(a);
+        assert_eq!(database.stats().in_use, 0);
+        assert_eq!(database.unload_unused(), 1);
+        assert_eq!(database.stats().loaded, 0);
+    }
+
+    #[test]
+    fn unloading_a_resource_can_leave_others_unused() {
+        let temp = tempfile::tempdir().unwrap();
+        let (database, uuids, _) = database_of(temp.path(), &["a", "b"]);
+        let a = database.get(&uuids[0]).unwrap();
+        let b = database.get(&uuids[1]).unwrap();
+        // `a` holds on to `b`, like a format loaded with the formats it refers to.
+        a.set(Resource {
+            data: ResourceData::Format(FormatDefinition::new(
+                "a".into(),
+                None,
+                Format::External(ExternalResource {
+                    uuid: uuids[1],
+                    handle: Some(b),
+                }),
+            )),
+            missing: false,
+        });
+        drop(a);
+
+        assert_eq!(database.stats().in_use, 1);
+        assert_eq!(database.unload_unused(), 2);
+        assert_eq!(database.stats().loaded, 0);
+    }
+}
@@ -0,0 +1,468 @@
+//! Creating, moving, duplicating and deleting resources.
+//!
+//! Files are changed right away, and the database is updated without waiting for a
+//! [`ResourceWatcher`](super::ResourceWatcher) to notice. Resources contributed by plugins are
+//! read-only: they can't be moved or deleted, but they can be duplicated into the project.
+//!
+//! Resources are only ever written to new JSON files inside of the project's folder, and never
+//! inside of the folders the project doesn't scan (like the one its plugins are in).
+
+use super::{
+    CustomResourceData, ExternalResource, Resource, ResourceData, ResourceDatabase, ResourceEntry,
+    ResourceEvent, ResourceLoadError,
+};
+use crate::behaviour::{BehaviourDescriptor, custom::CustomBlockDefinition};
+use crate::format::{BaseType, Format, FormatDefinition, validate::default_json};
+use crate::project::{object::ObjectRecipe, room::RoomDescriptor};
+use serde_json::{Map, Value};
+use std::{
+    collections::HashMap,
+    path::{Component, Path, PathBuf},
+};
+use thiserror::Error;
+use uuid::Uuid;
+
+/// A kind of resource that can be created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceKind {
+    Format,
+    ObjectRecipe,
+    Room,
+    /// A resource holding data in a format of the project.
+    Custom {
+        format: Uuid,
+    },
+    Block,
+    Behaviour,
+}
+
+/// Error for when the resources of a project can't be changed.
+#[derive(Debug, Error)]
+pub enum ResourceEditError {
+    #[error("There is no resource with the UUID {0}")]
+    DoesNotExist(Uuid),
+    #[error("The resource {0} comes from a plugin, so it can't be changed")]
+    ReadOnly(Uuid),
+    #[error("There is already a file at {}", .0.display())]
+    AlreadyExists(PathBuf),
+    #[error("The resource {0} isn't a format, so there can't be data in it")]
+    NotAFormat(Uuid),
+    #[error("{} isn't a place for a resource of the project", .0.display())]
+    OutsideProject(PathBuf),
+    #[error("{} isn't a JSON file, so it can't hold a resource", .0.display())]
+    NotJson(PathBuf),
+    #[error(
+        "The resource {uuid} is still used by {}",
+        used_by
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ")
+    )]
+    StillUsed { uuid: Uuid, used_by: Vec<Uuid> },
+    #[error("Failed to read the resource ({0})")]
+    Load(#[from] ResourceLoadError),
+    #[error("Failed to write the resource ({0})")]
+    File(#[from] std::io::Error),
+    #[error("Failed to write the resource ({0})")]
+    Serialize(#[from] serde_json::Error),
+}
+
+impl ResourceDatabase {
+    /// Creates a resource of a kind in a new file, and returns a reference to it.
+    ///
+    /// The resource is named after its file, and is otherwise empty; the data of a custom
+    /// resource starts out as the default value of its format.
+    pub fn create(
+        &self,
+        kind: ResourceKind,
+        path: PathBuf,
+    ) -> Result<ExternalResource, ResourceEditError> {
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let data = match kind {
+            ResourceKind::Format => {
+                ResourceData::Format(FormatDefinition::new(name, None, Format::Tuple(Vec::new())))
+            }
+            ResourceKind::ObjectRecipe => ResourceData::ObjectRecipe(ObjectRecipe::new(name)),
+            ResourceKind::Room => ResourceData::Room(RoomDescriptor::new(name)),
+            ResourceKind::Custom { format } => {
+                if !self.uuids().contains(&format) {
+                    return Err(ResourceEditError::DoesNotExist(format));
+                }
+                if !matches!(
+                    self.get(&format)?.lock_ref().data(),
+                    ResourceData::Format(_)
+                ) {
+                    return Err(ResourceEditError::NotAFormat(format));
+                }
+                let data = default_json(
+                    &Format::External(ExternalResource {
+                        uuid: format,
+                        handle: None,
+                    }),
+                    self,
+                );
+                ResourceData::Custom(CustomResourceData::new(format, data))
+            }
+            ResourceKind::Block => ResourceData::Block(CustomBlockDefinition {
+                phrase: name.clone(),
+                name,
+                description: String::new(),
+                parameters: Vec::new(),
+                output: Format::BaseType(BaseType::Void),
+                variables: Vec::new(),
+                body: Default::default(),
+            }),
+            ResourceKind::Behaviour => ResourceData::Behaviour(BehaviourDescriptor::new()),
+        };
+
+        let uuid = Uuid::new_v4();
+        let resource = Resource {
+            data,
+            missing: false,
+        };
+        self.add_file(uuid, path, serde_json::to_value(resource)?)?;
+        Ok(ExternalResource { uuid, handle: None })
+    }
+
+    /// Moves the file of a resource. Handles to the resource keep working.
+    pub fn move_resource(&self, uuid: &Uuid, path: PathBuf) -> Result<(), ResourceEditError> {
+        let old_path = self.editable_path(uuid)?;
+        self.check_new_path(&path)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::rename(&old_path, &path)?;
+
+        if let Some(entry) = self.state_mut().resources.get_mut(uuid) {
+            entry.path = path.clone();
+        }
+        self.publish(ResourceEvent::Moved {
+            uuid: *uuid,
+            from: old_path,
+            to: path,
+        });
+        Ok(())
+    }
+
+    /// Copies a resource into a new file, and returns a reference to the copy.
+    ///
+    /// The copy gets a UUID of its own, and so does every resource embedded in it. References
+    /// the resource makes to itself (like a recursive block) are made to the copy instead.
+    pub fn duplicate(
+        &self,
+        uuid: &Uuid,
+        path: PathBuf,
+    ) -> Result<ExternalResource, ResourceEditError> {
+        let entry = self
+            .state()
+            .resources
+            .get(uuid)
+            .filter(|entry| !entry.missing)
+            .cloned()
+            .ok_or(ResourceEditError::DoesNotExist(*uuid))?;
+        let mut value = self.read_json(&entry)?;
+
+        let mut new_uuids = HashMap::from([(*uuid, Uuid::new_v4())]);
+        embedded_uuids(&value, &mut new_uuids);
+        replace_references(&mut value, &new_uuids);
+
+        let new_uuid = new_uuids[uuid];
+        self.add_file(new_uuid, path, value)?;
+        Ok(ExternalResource {
+            uuid: new_uuid,
+            handle: None,
+        })
+    }
+
+    /// Deletes the file of a resource, and removes it from the database.
+    ///
+    /// A resource that other resources still use isn't deleted, unless `force` is set. Handles
+    /// to the resource keep their last value, which is marked as missing.
+    pub fn delete(&self, uuid: &Uuid, force: bool) -> Result<(), ResourceEditError> {
+        let path = self.editable_path(uuid)?;
+        if !force {
+            let used_by = self.reference_graph().used_by(uuid);
+            if !used_by.is_empty() {
+                return Err(ResourceEditError::StillUsed {
+                    uuid: *uuid,
+                    used_by,
+                });
+            }
+        }
+        match std::fs::remove_file(&path) {
+            Err(error) if error.kind() != std::io::ErrorKind::NotFound => return Err(error.into()),
+            _ => {}
+        }
+
+        let (entry, duplicates) = {
+            let mut state = self.state_mut();
+            (
+                state.resources.remove(uuid),
+                state.duplicates.remove(uuid).unwrap_or_default(),
+            )
+        };
+        if let Some(loaded_resource_data) = entry.and_then(|entry| entry.data.get_cloned()) {
+            loaded_resource_data.lock_mut().missing = true;
+        }
+        self.publish(ResourceEvent::Removed { uuid: *uuid, path });
+        // Files that claimed the UUID too are read as the resource from now on.
+        for duplicate in duplicates {
+            self.patch_entry_from_path(duplicate);
+        }
+        Ok(())
+    }
+
+    /// Returns the file of a resource the project can change.
+    fn editable_path(&self, uuid: &Uuid) -> Result<PathBuf, ResourceEditError> {
+        let state = self.state();
+        let entry = state
+            .resources
+            .get(uuid)
+            .ok_or(ResourceEditError::DoesNotExist(*uuid))?;
+        if entry.plugin.is_some() {
+            return Err(ResourceEditError::ReadOnly(*uuid));
+        }
+        Ok(entry.path.clone())
+    }
+
+    /// Checks that a resource can be written to a new file: a JSON file, that doesn't exist yet,
+    /// inside of the project's folder but not inside of the folders the project doesn't scan
+    /// (like the one plugins are in).
+    fn check_new_path(&self, path: &Path) -> Result<(), ResourceEditError> {
+        if path.extension().is_none_or(|extension| extension != "json") {
+            return Err(ResourceEditError::NotJson(path.to_path_buf()));
+        }
+        let state = self.state();
+        let is_inside = !path
+            .components()
+            .any(|component| component == Component::ParentDir)
+            && state
+                .base_path
+                .as_ref()
+                .is_some_and(|base_path| path.starts_with(base_path))
+            && !state
+                .excluded
+                .iter()
+                .any(|excluded| path.starts_with(excluded));
+        if !is_inside {
+            return Err(ResourceEditError::OutsideProject(path.to_path_buf()));
+        }
+        if path.exists() {
+            return Err(ResourceEditError::AlreadyExists(path.to_path_buf()));
+        }
+        Ok(())
+    }
+
+    /// Reads the file of a resource, as the project sees it.
+    pub(super) fn read_json(&self, entry: &ResourceEntry) -> Result<Value, ResourceLoadError> {
+        match &entry.plugin {
+            Some(plugin_id) => self.read_plugin_json(&entry.path, plugin_id),
+            None => Ok(serde_json::from_str(&std::fs::read_to_string(
+                &entry.path,
+            )?)?),
+        }
+    }
+
+    /// Writes a resource to a new file under a UUID, and adds it to the database.
+    fn add_file(
+        &self,
+        uuid: Uuid,
+        path: PathBuf,
+        resource: Value,
+    ) -> Result<(), ResourceEditError> {
+        self.check_new_path(&path)?;
+        let mut file = Map::new();
+        file.insert("uuid".to_string(), Value::from(uuid.to_string()));
+        if let Value::Object(fields) = resource {
+            file.extend(fields.into_iter().filter(|(key, _)| key != "uuid"));
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, serde_json::to_string_pretty(&file)?)?;
+
+        self.state_mut().resources.insert(
+            uuid,
+            ResourceEntry {
+                path: path.clone(),
+                ..Default::default()
+            },
+        );
+        self.publish(ResourceEvent::Created { uuid, path });
+        Ok(())
+    }
+}
+
+/// Gives a new UUID to every resource embedded in a value.
+fn embedded_uuids(value: &Value, new_uuids: &mut HashMap<Uuid, Uuid>) {
+    match value {
+        Value::Array(values) => values
+            .iter()
+            .for_each(|value| embedded_uuids(value, new_uuids)),
+        Value::Object(fields) => {
+            if fields.get("storage").and_then(Value::as_str) == Some("Embedded")
+                && let Some(Ok(uuid)) = fields
+                    .get("uuid")
+                    .and_then(Value::as_str)
+                    .map(Uuid::parse_str)
+            {
+                new_uuids.entry(uuid).or_insert_with(Uuid::new_v4);
+            }
+            fields
+                .values()
+                .for_each(|value| embedded_uuids(value, new_uuids));
+        }
+        Value::Null | Value::Bool(_) | Value::Number(_) | Value::String(_) => {}
+    }
+}
+
+/// Rewrites the fields of a value that refer to some UUIDs to the UUIDs that replace them.
+///
+/// Like in [`references`](super::graph::references), only `uuid` fields and `custom:` block
+/// sources are rewritten; other text is left alone, even if it reads like one of the UUIDs.
+fn replace_references(value: &mut Value, new_uuids: &HashMap<Uuid, Uuid>) {
+    let new_uuid = |text: &str| {
+        Uuid::parse_str(text)
+            .ok()
+            .and_then(|uuid| new_uuids.get(&uuid))
+    };
+
+    match value {
+        Value::String(text) => {
+            if let Some(new_uuid) = text.strip_prefix("custom:").and_then(new_uuid) {
+                *text = format!("custom:{new_uuid}");
+            }
+        }
+        Value::Array(values) => values
+            .iter_mut()
+            .for_each(|value| replace_references(value, new_uuids)),
+        Value::Object(fields) => {
+            for (key, value) in fields {
+                match value {
+                    Value::String(text) if key == "uuid" => {
+                        if let Some(new_uuid) = new_uuid(text) {
+                            *text = new_uuid.to_string();
+                        }
+                    }
+                    value => replace_references(value, new_uuids),
+                }
+            }
+        }
+        Value::Null | Value::Bool(_) | Value::Number(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Makes a database for the project in `<temp>/project`, whose plugins are in `plugins`.
+    fn project(temp: &Path) -> (PathBuf, ResourceDatabase) {
+        let project = temp.join("project");
+        std::fs::create_dir_all(project.join("plugins")).unwrap();
+        let database =
+            ResourceDatabase::from_directory_excluding(project.clone(), &[project.join("plugins")]);
+        (project, database)
+    }
+
+    #[test]
+    fn creates_resources_only_in_json_files_of_the_project() {
+        let temp = tempfile::tempdir().unwrap();
+        let (project, database) = project(temp.path());
+
+        let outside = [
+            temp.path().join("stray.json"),
+            project.join("..").join("stray.json"),
+            project.join("plugins").join("stray.json"),
+        ];
+        for path in outside {
+            assert!(matches!(
+                database.create(ResourceKind::Format, path),
+                Err(ResourceEditError::OutsideProject(_))
+            ));
+        }
+        assert!(matches!(
+            database.create(ResourceKind::Format, project.join("item.txt")),
+            Err(ResourceEditError::NotJson(_))
+        ));
+
+        let behaviour = database
+            .create(ResourceKind::Behaviour, project.join("behaviour.json"))
+            .unwrap();
+        assert!(matches!(
+            database.create(
+                ResourceKind::Custom {
+                    format: behaviour.uuid
+                },
+                project.join("item.json")
+            ),
+            Err(ResourceEditError::NotAFormat(_))
+        ));
+    }
+
+    #[test]
+    fn deletes_used_resources_only_when_forced() {
+        let temp = tempfile::tempdir().unwrap();
+        let (project, database) = project(temp.path());
+        let format = database
+            .create(ResourceKind::Format, project.join("item.json"))
+            .unwrap();
+        let potion = database
+            .create(
+                ResourceKind::Custom {
+                    format: format.uuid,
+                },
+                project.join("potion.json"),
+            )
+            .unwrap();
+
+        match database.delete(&format.uuid, false) {
+            Err(ResourceEditError::StillUsed { used_by, .. }) => {
+                assert_eq!(used_by, [potion.uuid])
+            }
+            other => panic!("expected the format to still be used, got {other:?}"),
+        }
+        assert!(project.join("item.json").exists());
+
+        database.delete(&format.uuid, true).unwrap();
+        assert!(!project.join("item.json").exists());
+        assert!(!database.uuids().contains(&format.uuid));
+    }
+
+    #[test]
+    fn duplicates_only_rewrite_reference_fields() {
+        let temp = tempfile::tempdir().unwrap();
+        let (project, _) = project(temp.path());
+        let uuid = Uuid::new_v4();
+        let block = json!({
+            "uuid": uuid,
+            "type": "Block",
+            "data": {
+                "name": "Forever",
+                "phrase": "forever",
+                "body": { "blocks": [
+                    { "source": "builtin:log", "what": uuid.to_string() },
+                    { "source": format!("custom:{uuid}") }
+                ] }
+            }
+        });
+        std::fs::write(project.join("forever.json"), block.to_string()).unwrap();
+        let database =
+            ResourceDatabase::from_directory_excluding(project.clone(), &[project.join("plugins")]);
+
+        let copy = database
+            .duplicate(&uuid, project.join("copy.json"))
+            .unwrap();
+        let text = std::fs::read_to_string(project.join("copy.json")).unwrap();
+        let value: Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(value["uuid"], json!(copy.uuid.to_string()));
+        let blocks = &value["data"]["body"]["blocks"];
+        assert_eq!(blocks[0]["what"], json!(uuid.to_string()));
+        assert_eq!(blocks[1]["source"], json!(format!("custom:{}", copy.uuid)));
+    }
+}
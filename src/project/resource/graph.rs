@@ -0,0 +1,318 @@
+//! The references resources make to each other.
+//!
+//! Resources refer to each other by UUID, through [`ExternalResource`](super::ExternalResource)s
+//! and `custom:` block sources. A [`ReferenceGraph`] gathers every reference at once, so that an
+//! editor can tell what uses a resource, and what is broken: references to resources that don't
+//! exist (or whose files were removed), resources nothing uses, and formats that are made out of
+//! themselves.
+
+use super::{ResourceData, ResourceDatabase};
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use uuid::Uuid;
+
+/// The references between the resources of a database, as they were when it was built.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReferenceGraph {
+    /// The resources each resource refers to.
+    depends_on: HashMap<Uuid, BTreeSet<Uuid>>,
+    /// The resources that refer to each resource, including resources that don't exist.
+    used_by: HashMap<Uuid, BTreeSet<Uuid>>,
+    /// The resources that are format definitions.
+    formats: HashSet<Uuid>,
+    /// The resources that come from plugins.
+    read_only: HashSet<Uuid>,
+}
+
+/// A reference to a resource that doesn't exist, or whose file was removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DanglingReference {
+    /// The resource that makes the reference.
+    pub from: Uuid,
+    /// The UUID it refers to.
+    pub to: Uuid,
+}
+
+impl ResourceDatabase {
+    /// Gathers the references between every resource of the database.
+    ///
+    /// Loaded resources are walked as they are in memory; the others are read from their files,
+    /// without loading them. Resources whose files can't be read don't refer to anything.
+    pub fn reference_graph(&self) -> ReferenceGraph {
+        let entries = self
+            .state()
+            .resources
+            .iter()
+            .filter(|(_, entry)| !entry.missing)
+            .map(|(uuid, entry)| (*uuid, entry.clone()))
+            .collect::<Vec<_>>();
+
+        let mut graph = ReferenceGraph::default();
+        for (uuid, entry) in entries {
+            let (value, is_format) = match entry.data.get_cloned() {
+                Some(loaded_resource_data) => {
+                    let resource = loaded_resource_data.lock_ref();
+                    (
+                        serde_json::to_value(&*resource).ok(),
+                        matches!(resource.data, ResourceData::Format(_)),
+                    )
+                }
+                None => {
+                    let value = self.read_json(&entry).ok();
+                    let is_format = value
+                        .as_ref()
+                        .and_then(|value| value.get("type"))
+                        .and_then(Value::as_str)
+                        == Some("Format");
+                    (value, is_format)
+                }
+            };
+
+            if is_format {
+                graph.formats.insert(uuid);
+            }
+            if entry.plugin.is_some() {
+                graph.read_only.insert(uuid);
+            }
+            let depends_on = value.as_ref().map(references).unwrap_or_default();
+            for other in &depends_on {
+                graph.used_by.entry(*other).or_default().insert(uuid);
+            }
+            graph
+                .depends_on
+                .insert(uuid, depends_on.into_iter().collect());
+        }
+        graph
+    }
+}
+
+impl ReferenceGraph {
+    /// Returns the resources a resource refers to, sorted by UUID.
+    pub fn depends_on(&self, uuid: &Uuid) -> Vec<Uuid> {
+        self.depends_on
+            .get(uuid)
+            .map(|uuids| uuids.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Returns the resources that refer to a resource, sorted by UUID.
+    pub fn used_by(&self, uuid: &Uuid) -> Vec<Uuid> {
+        self.used_by
+            .get(uuid)
+            .map(|uuids| {
+                uuids
+                    .iter()
+                    .filter(|other| *other != uuid)
+                    .copied()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns the references made to resources that aren't in the graph.
+    pub fn dangling(&self) -> Vec<DanglingReference> {
+        let mut dangling = self
+            .depends_on
+            .iter()
+            .flat_map(|(from, uuids)| {
+                uuids
+                    .iter()
+                    .filter(|to| !self.depends_on.contains_key(to))
+                    .map(|to| DanglingReference {
+                        from: *from,
+                        to: *to,
+                    })
+            })
+            .collect::<Vec<_>>();
+        dangling.sort();
+        dangling
+    }
+
+    /// Returns the resources of the project that can't be reached by following references from
+    /// some resources, sorted by UUID.
+    ///
+    /// Resources that come from plugins aren't reported, as a plugin may offer more than a
+    /// project uses.
+    pub fn unreachable(&self, roots: impl IntoIterator<Item = Uuid>) -> Vec<Uuid> {
+        let mut reached = HashSet::new();
+        let mut pending = roots.into_iter().collect::<Vec<_>>();
+        while let Some(uuid) = pending.pop() {
+            if reached.insert(uuid)
+                && let Some(uuids) = self.depends_on.get(&uuid)
+            {
+                pending.extend(uuids);
+            }
+        }
+
+        let mut unreachable = self
+            .depends_on
+            .keys()
+            .filter(|uuid| !reached.contains(uuid) && !self.read_only.contains(uuid))
+            .copied()
+            .collect::<Vec<_>>();
+        unreachable.sort();
+        unreachable
+    }
+
+    /// Returns the groups of format definitions that are made out of each other, each sorted by
+    /// UUID.
+    ///
+    /// A format that refers to itself is a group of its own.
+    pub fn format_cycles(&self) -> Vec<Vec<Uuid>> {
+        let mut formats = self.formats.iter().copied().collect::<Vec<_>>();
+        formats.sort();
+
+        let mut search = CycleSearch {
+            graph: self,
+            index: HashMap::new(),
+            low_link: HashMap::new(),
+            stack: Vec::new(),
+            on_stack: HashSet::new(),
+            cycles: Vec::new(),
+        };
+        for format in formats {
+            if !search.index.contains_key(&format) {
+                search.visit(format);
+            }
+        }
+
+        let mut cycles = search.cycles;
+        cycles.iter_mut().for_each(|cycle| cycle.sort());
+        cycles.sort();
+        cycles
+    }
+
+    /// Returns the formats a format refers to.
+    fn format_dependencies(&self, uuid: &Uuid) -> impl Iterator<Item = Uuid> + '_ {
+        self.depends_on
+            .get(uuid)
+            .into_iter()
+            .flatten()
+            .filter(|other| self.formats.contains(other))
+            .copied()
+    }
+}
+
+/// Finds the strongly connected components of the graph of formats (as in Tarjan's algorithm).
+struct CycleSearch<'g> {
+    graph: &'g ReferenceGraph,
+    index: HashMap<Uuid, usize>,
+    low_link: HashMap<Uuid, usize>,
+    stack: Vec<Uuid>,
+    on_stack: HashSet<Uuid>,
+    cycles: Vec<Vec<Uuid>>,
+}
+
+impl CycleSearch<'_> {
+    fn visit(&mut self, uuid: Uuid) {
+        let index = self.index.len();
+        self.index.insert(uuid, index);
+        self.low_link.insert(uuid, index);
+        self.stack.push(uuid);
+        self.on_stack.insert(uuid);
+
+        for other in self.graph.format_dependencies(&uuid) {
+            if !self.index.contains_key(&other) {
+                self.visit(other);
+                let low_link = self.low_link[&uuid].min(self.low_link[&other]);
+                self.low_link.insert(uuid, low_link);
+            } else if self.on_stack.contains(&other) {
+                let low_link = self.low_link[&uuid].min(self.index[&other]);
+                self.low_link.insert(uuid, low_link);
+            }
+        }
+
+        if self.low_link[&uuid] == index {
+            let mut component = Vec::new();
+            while let Some(other) = self.stack.pop() {
+                self.on_stack.remove(&other);
+                component.push(other);
+                if other == uuid {
+                    break;
+                }
+            }
+            let refers_to_itself = self.graph.format_dependencies(&uuid).any(|u| u == uuid);
+            if component.len() > 1 || refers_to_itself {
+                self.cycles.push(component);
+            }
+        }
+    }
+}
+
+/// Returns the UUIDs of the resources a value refers to.
+///
+/// Only the fields that hold references are followed: the `uuid` of an
+/// [`ExternalResource`](super::ExternalResource) (like an external format, or a reference in the
+/// data of a custom resource) and `custom:` block sources. Other text is never taken for a
+/// reference, even if it reads like a UUID. The UUID a resource file gives its own resource, and
+/// the UUIDs of the resources embedded in it, aren't references either.
+pub fn references(value: &Value) -> HashSet<Uuid> {
+    fn collect(value: &Value, found: &mut HashSet<Uuid>, embedded: &mut HashSet<Uuid>) {
+        match value {
+            Value::String(text) => {
+                if let Some(Ok(uuid)) = text.strip_prefix("custom:").map(Uuid::parse_str) {
+                    found.insert(uuid);
+                }
+            }
+            Value::Array(values) => values
+                .iter()
+                .for_each(|value| collect(value, found, embedded)),
+            Value::Object(fields) => {
+                let is_embedded = fields.get("storage").and_then(Value::as_str) == Some("Embedded");
+                for (key, value) in fields {
+                    match value.as_str().map(Uuid::parse_str) {
+                        Some(Ok(uuid)) if key == "uuid" && is_embedded => {
+                            embedded.insert(uuid);
+                        }
+                        Some(Ok(uuid)) if key == "uuid" => {
+                            found.insert(uuid);
+                        }
+                        _ => collect(value, found, embedded),
+                    }
+                }
+            }
+            Value::Null | Value::Bool(_) | Value::Number(_) => {}
+        }
+    }
+
+    let mut found = HashSet::new();
+    let mut embedded = HashSet::new();
+    match value {
+        Value::Object(fields) => fields
+            .iter()
+            .filter(|(key, _)| *key != "uuid")
+            .for_each(|(_, value)| collect(value, &mut found, &mut embedded)),
+        value => collect(value, &mut found, &mut embedded),
+    }
+    found.retain(|uuid| !embedded.contains(uuid));
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use uuid::uuid;
+
+    #[test]
+    fn only_reference_fields_are_followed() {
+        let format = uuid!("0c4e7f52-8a1d-4b36-9e2f-6d5a3c7b1e90");
+        let block = uuid!("7f1c5e08-d94b-4a2e-b6c3-58e0a9f2d714");
+        let item = uuid!("a3d9b1f4-2c6e-4f87-8b05-e1c7d24f6a38");
+        let text = "9b2f6c1e-4d7a-4e58-b3c1-2a6f0d8e7c45";
+        let resource = json!({
+            "uuid": "5e0c8a3d-71f2-4b9e-a6d4-3f1b2c9e8a70",
+            "type": "Custom",
+            "data": {
+                "format": { "uuid": format.to_string() },
+                "data": {
+                    "name": text,
+                    "tags": [text],
+                    "item": { "uuid": item.to_string() },
+                },
+            },
+            "script": { "source": format!("custom:{block}"), "what": text },
+        });
+        assert_eq!(references(&resource), HashSet::from([format, block, item]));
+    }
+}
@@ -5,24 +5,51 @@
 //! but you can also create your own definitions.
 
 use super::{object::ObjectRecipe, room::RoomDescriptor};
-use crate::format::FormatDefinition;
-use futures_signals::signal::Mutable;
+use crate::behaviour::{BehaviourDescriptor, custom::CustomBlockDefinition};
+use crate::format::{
+    Format, FormatDefinition, VariantValue,
+    validate::{ValidationError, decode_json},
+};
+use futures_signals::signal::{
+    Mutable, MutableLockMut, MutableLockRef, MutableSignalCloned, MutableSignalRef,
+};
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard,
+        atomic::AtomicU64,
+        mpsc::{Receiver, Sender},
+    },
+    thread::{self, ThreadId},
 };
 use thiserror::Error;
 use uuid::Uuid;
 use walkdir::WalkDir;
 
+mod cache;
+mod edit;
+mod graph;
+mod plugins;
+mod watch;
+
+pub use cache::{DEFAULT_MEMORY_BUDGET, ResourceStats};
+pub use edit::{ResourceEditError, ResourceKind};
+pub use graph::{DanglingReference, ReferenceGraph, references};
+pub use watch::{DEBOUNCE, ResourceEvent, ResourceWatcher};
+
 /// Error for when a resource fails to load.
 #[derive(Debug, Error)]
 #[error("Error loading the Resource from disk.")]
 pub enum ResourceLoadError {
     DoesNotExist,
+    /// The file of the resource was removed.
+    Missing,
     File(#[from] std::io::Error),
     Deserialize(#[from] serde_json::Error),
+    /// The data of the resource doesn't fit its format.
+    Invalid(Vec<ValidationError>),
 }
 
 /// Error for when a resource fails to save.
@@ -41,41 +68,122 @@ pub enum ResourceSaveError {
 /// The API allows the editor or the game to get handles to the resource that all share the _same_
 /// underlying value, observe changes on that value.
 ///
-/// Futurely, there will be a behaviour to unload resources when they are no longer in use.
-#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+/// Clones of a database share it, so a database can be watched and read from other threads.
+///
+/// Resources that no handles are left to are unloaded once the loaded resources weigh more than
+/// the [memory budget](ResourceDatabase::set_memory_budget), least recently used first.
+#[derive(Default, Debug, Clone)]
 pub struct ResourceDatabase {
+    state: Arc<RwLock<DatabaseState>>,
+    /// Where [`ResourceEvent`]s are sent.
+    subscribers: Arc<Mutex<Vec<Sender<ResourceEvent>>>>,
+}
+
+#[derive(Debug)]
+struct DatabaseState {
     resources: HashMap<Uuid, ResourceEntry>,
+    /// The UUIDs the resources of each merged plugin have in their own files, by plugin id.
+    plugins: HashMap<String, HashSet<Uuid>>,
+    /// The folder that was scanned for resources, if the database was made from one.
+    base_path: Option<PathBuf>,
+    /// The folders that aren't scanned for resources of the project.
+    excluded: Vec<PathBuf>,
+    /// The files that are ignored because another file has their UUID.
+    duplicates: HashMap<Uuid, Vec<PathBuf>>,
+    /// How much loaded resources may weigh before unused ones are unloaded, in bytes.
+    memory_budget: u64,
+    /// Ticks every time a resource is used, to tell which were used least recently.
+    clock: AtomicU64,
+}
+
+impl Default for DatabaseState {
+    fn default() -> Self {
+        DatabaseState {
+            resources: HashMap::new(),
+            plugins: HashMap::new(),
+            base_path: None,
+            excluded: Vec::new(),
+            duplicates: HashMap::new(),
+            memory_budget: DEFAULT_MEMORY_BUDGET,
+            clock: AtomicU64::new(0),
+        }
+    }
+}
+
+/// Files that claim the same UUID.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateResource {
+    pub uuid: Uuid,
+    /// The file the resource is read from.
+    pub path: PathBuf,
+    /// The other files that claim the UUID, which are ignored.
+    pub ignored: Vec<PathBuf>,
 }
 
 impl ResourceDatabase {
     /// Scans a directory, finds all resources,
     /// and sets up hot reloading.
     pub fn from_directory(base_path: PathBuf) -> Self {
-        let mut database = ResourceDatabase {
-            resources: HashMap::new(),
+        Self::from_directory_excluding(base_path, &[])
+    }
+
+    /// Scans a directory, finds all resources except the ones inside some of its folders,
+    /// and sets up hot reloading.
+    ///
+    /// When several files claim the same UUID, the first one (in path order) is used and the
+    /// others are ignored, as [`ResourceDatabase::duplicates`].
+    pub fn from_directory_excluding(base_path: PathBuf, excluded: &[PathBuf]) -> Self {
+        let mut state = DatabaseState {
+            base_path: Some(base_path.clone()),
+            excluded: excluded.to_vec(),
+            ..Default::default()
         };
 
-        for entry in WalkDir::new(base_path).into_iter().filter_map(|x| x.ok()) {
+        let entries = WalkDir::new(base_path)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|entry| !excluded.iter().any(|path| entry.path() == path))
+            .filter_map(|x| x.ok());
+        for entry in entries {
             let sample = Self::get_sample(entry.path());
             if let Some(sample) = sample {
-                database.resources.insert(
+                if state.resources.contains_key(&sample.uuid) {
+                    let ignored = state.duplicates.entry(sample.uuid).or_default();
+                    ignored.push(entry.path().to_path_buf());
+                    continue;
+                }
+                state.resources.insert(
                     sample.uuid,
                     ResourceEntry {
                         path: entry.path().to_path_buf(),
-                        data: None,
+                        ..Default::default()
                     },
                 );
             }
         }
 
-        database
+        ResourceDatabase {
+            state: Arc::new(RwLock::new(state)),
+            ..Default::default()
+        }
+    }
+
+    /// Locks the database for reading.
+    fn state(&self) -> RwLockReadGuard<'_, DatabaseState> {
+        // The state is never left half-changed, so it's still usable after a panic.
+        self.state.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Locks the database for writing.
+    fn state_mut(&self) -> RwLockWriteGuard<'_, DatabaseState> {
+        self.state.write().unwrap_or_else(PoisonError::into_inner)
     }
 
     /// Samples a file to check for a resource!
     fn get_sample<P: AsRef<Path>>(path: P) -> Option<ResourceSample> {
         let path = path.as_ref();
 
-        if !(path.is_file() && path.extension().map_or(false, |ext| ext == "json")) {
+        if !(path.is_file() && path.extension().is_some_and(|ext| ext == "json")) {
             return None;
         }
         if path.ends_with("project.json") {
@@ -105,94 +213,388 @@ impl ResourceDatabase {
         Some(sample)
     }
 
-    /// Watches over a directory and updates resources whenever they change.
-    pub fn watch(&mut self, base_path: PathBuf) -> Result<(), notify::Error> {
-        use notify::Watcher as _;
-
-        let (tx, rx) = std::sync::mpsc::channel::<notify::Result<notify::Event>>();
-        let mut watcher = notify::recommended_watcher(tx)?;
-        watcher.watch(base_path.as_path(), notify::RecursiveMode::Recursive)?;
-
-        for res in rx {
-            match res {
-                // TODO: Decide the rules for hot reloading!
-                Ok(event) => match event.kind {
-                    notify::EventKind::Any => {}
-                    notify::EventKind::Access(_) => {}
-                    notify::EventKind::Create(_) => {
-                        event.paths.iter().for_each(|path| {
-                            self.patch_entry_from_path(path.clone());
-                        });
-                    }
-                    notify::EventKind::Modify(modify_kind) => match modify_kind {
-                        notify::event::ModifyKind::Any => {}
-                        _ => {
-                            event.paths.iter().for_each(|path| {
-                                self.patch_entry_from_path(path.clone());
-                            });
-                        }
-                    },
-                    notify::EventKind::Remove(_) => todo!(),
-                    notify::EventKind::Other => todo!(),
+    /// Returns a receiver of every change made to the resources of the database from now on.
+    ///
+    /// Receivers that are dropped are forgotten.
+    pub fn subscribe(&self) -> Receiver<ResourceEvent> {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(sender);
+        receiver
+    }
+
+    /// Sends a change to every subscriber.
+    fn publish(&self, event: ResourceEvent) {
+        self.subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+
+    /// Finds the resource in a file that was created or changed, and reloads it if it's loaded.
+    ///
+    /// A resource that is found at a new path was moved, unless its old file is still there:
+    /// then the new file is a duplicate, and it's ignored.
+    pub fn patch_entry_from_path(&self, path: PathBuf) {
+        // Plugin resources are found by path, as their files hold the plugin's own UUIDs.
+        let plugin_resource = self
+            .state()
+            .resources
+            .iter()
+            .find(|(_, entry)| entry.plugin.is_some() && entry.path == path)
+            .map(|(uuid, _)| ResourceSample { uuid: *uuid });
+        if plugin_resource.is_none()
+            && self
+                .state()
+                .excluded
+                .iter()
+                .any(|excluded| path.starts_with(excluded))
+        {
+            return;
+        }
+
+        let Some(sample) = plugin_resource.or_else(|| Self::get_sample(path.as_path())) else {
+            return;
+        };
+        let uuid = sample.uuid;
+        // TODO: Use `tracing` for this!
+        println!("Hot Reloading {}", path.display());
+
+        let mut state = self.state_mut();
+        // The file may have been a duplicate of another resource before.
+        for (other_uuid, ignored) in state.duplicates.iter_mut() {
+            if *other_uuid != uuid {
+                ignored.retain(|ignored| *ignored != path);
+            }
+        }
+        state.duplicates.retain(|_, ignored| !ignored.is_empty());
+
+        let Some(existing_entry) = state.resources.get(&uuid) else {
+            state.resources.insert(
+                uuid,
+                ResourceEntry {
+                    path: path.clone(),
+                    ..Default::default()
                 },
-                // TODO: Use `tracing` for this.
-                Err(e) => eprintln!("Error watching resources: {} {}", base_path.display(), e),
+            );
+            drop(state);
+            self.publish(ResourceEvent::Created { uuid, path });
+            return;
+        };
+
+        let is_duplicate = existing_entry.path != path
+            && !existing_entry.missing
+            && Self::get_sample(&existing_entry.path).is_some_and(|sample| sample.uuid == uuid);
+        if is_duplicate {
+            let ignored = state.duplicates.entry(uuid).or_default();
+            if !ignored.contains(&path) {
+                ignored.push(path.clone());
+                drop(state);
+                self.publish(ResourceEvent::Duplicated { uuid, path });
             }
+            return;
         }
 
-        Ok(())
+        // The entry is updated at once, so that nobody sees a resource that moved half-way.
+        let Some(existing_entry) = state.resources.get_mut(&uuid) else {
+            return;
+        };
+        let old_path = std::mem::replace(&mut existing_entry.path, path.clone());
+        let was_missing = std::mem::replace(&mut existing_entry.missing, false);
+        let plugin_id = existing_entry.plugin.clone();
+        let loaded_resource_data = existing_entry.data.get_cloned();
+        drop(state);
+
+        if old_path != path {
+            self.publish(ResourceEvent::Moved {
+                uuid,
+                from: old_path,
+                to: path.clone(),
+            });
+        } else if was_missing {
+            self.publish(ResourceEvent::Created {
+                uuid,
+                path: path.clone(),
+            });
+        } else if loaded_resource_data.is_none() {
+            self.publish(ResourceEvent::Modified { uuid });
+        }
+
+        if let Some(loaded_resource_data) = loaded_resource_data {
+            match self.read(path.as_path(), plugin_id.as_deref()) {
+                Ok(new_data) => {
+                    loaded_resource_data.set(new_data);
+                    if let Some(entry) = self.state().resources.get(&uuid) {
+                        entry.usage.weigh(&path);
+                    }
+                    self.publish(ResourceEvent::Modified { uuid });
+                }
+                Err(e) => {
+                    // TODO: Use `tracing` for this!
+                    eprintln!(
+                        "Failure to hot reload resource with UUID {} at {} because {:?}",
+                        uuid,
+                        path.display(),
+                        e
+                    );
+                    self.publish(ResourceEvent::ReloadFailed {
+                        uuid,
+                        error: Arc::new(e),
+                    });
+                }
+            }
+        }
     }
 
-    pub fn patch_entry_from_path(&mut self, path: PathBuf) {
-        let sample = Self::get_sample(path.as_path());
-        if let Some(sample) = sample {
-            // TODO: Use `tracing` for this!
-            println!("Hot Reloading {}", path.display());
-
-            if let Some(existing_entry) = self.resources.get_mut(&sample.uuid) {
-                existing_entry.path = path.clone();
-                if let Some(loaded_resource_data) = &existing_entry.data {
-                    match Resource::load(path.as_path()) {
-                        Ok(new_data) => loaded_resource_data.set(dbg!(new_data)),
-                        // TODO: Use `tracing` for this!
-                        Err(e) => eprintln!(
-                            "Failure to hot reload resource with UUID {} at {} because {:?}",
-                            sample.uuid,
-                            path.display(),
-                            e
-                        ),
+    /// Marks the resources in a file or folder that was removed as missing.
+    ///
+    /// Handles to the resources keep their last value, which is marked as missing too. If
+    /// another file claims the UUID of a resource that went missing, the resource is read from
+    /// that file instead.
+    pub fn forget_path(&self, path: &Path) {
+        let mut state = self.state_mut();
+        let gone = state
+            .resources
+            .iter()
+            .filter(|(_, entry)| {
+                !entry.missing && entry.path.starts_with(path) && !entry.path.exists()
+            })
+            .map(|(uuid, _)| *uuid)
+            .collect::<Vec<_>>();
+        for ignored in state.duplicates.values_mut() {
+            ignored.retain(|ignored| ignored.exists());
+        }
+        state.duplicates.retain(|_, ignored| !ignored.is_empty());
+
+        let mut removed = Vec::new();
+        let mut promoted = Vec::new();
+        for uuid in gone {
+            let duplicate = state
+                .duplicates
+                .get_mut(&uuid)
+                .filter(|ignored| !ignored.is_empty())
+                .map(|ignored| ignored.remove(0));
+            if state.duplicates.get(&uuid).is_some_and(Vec::is_empty) {
+                state.duplicates.remove(&uuid);
+            }
+            match duplicate {
+                Some(duplicate) => promoted.push(duplicate),
+                None => {
+                    if let Some(entry) = state.resources.get_mut(&uuid) {
+                        entry.missing = true;
+                        removed.push((uuid, entry.path.clone(), entry.data.get_cloned()));
                     }
                 }
-            } else {
-                self.resources.insert(
-                    sample.uuid,
-                    ResourceEntry {
-                        path: path.clone(),
-                        data: None,
-                    },
-                );
             }
         }
+        drop(state);
+
+        for (uuid, path, loaded_resource_data) in removed {
+            if let Some(loaded_resource_data) = loaded_resource_data {
+                loaded_resource_data.lock_mut().missing = true;
+            }
+            self.publish(ResourceEvent::Removed { uuid, path });
+        }
+        for path in promoted {
+            self.patch_entry_from_path(path);
+        }
+    }
+
+    /// Returns the files that claim the same UUID as another file.
+    pub fn duplicates(&self) -> Vec<DuplicateResource> {
+        let state = self.state();
+        state
+            .duplicates
+            .iter()
+            .filter_map(|(uuid, ignored)| {
+                Some(DuplicateResource {
+                    uuid: *uuid,
+                    path: state.resources.get(uuid)?.path.clone(),
+                    ignored: ignored.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// Returns whether the file of a resource was removed.
+    pub fn is_missing(&self, uuid: &Uuid) -> bool {
+        self.state()
+            .resources
+            .get(uuid)
+            .is_some_and(|entry| entry.missing)
+    }
+
+    /// Returns the UUIDs of every resource in the database (except for missing ones).
+    pub fn uuids(&self) -> Vec<Uuid> {
+        self.state()
+            .resources
+            .iter()
+            .filter(|(_, entry)| !entry.missing)
+            .map(|(uuid, _)| *uuid)
+            .collect()
+    }
+
+    /// Returns whether a resource can't be changed by the project, because it comes from a plugin.
+    pub fn is_read_only(&self, uuid: &Uuid) -> bool {
+        self.state()
+            .resources
+            .get(uuid)
+            .is_some_and(|entry| entry.plugin.is_some())
     }
 
     /// Loads an [`ExternalResource`] reference in place.
     pub fn load(&mut self, ext_resource: &mut ExternalResource) -> Result<(), ResourceLoadError> {
-        ext_resource.handle = Some(match self.resources.get_mut(&ext_resource.uuid) {
-            Some(resource) => resource.get_ref()?,
-            None => Err(ResourceLoadError::DoesNotExist)?,
-        });
+        ext_resource.handle = Some(self.get(&ext_resource.uuid)?);
         Ok(())
     }
+
+    /// Returns a handle to the resource with the given UUID, loading it if needed.
+    ///
+    /// Resources are decoded as they load: the data of a custom resource is checked against its
+    /// format, and the resource fails to load if it doesn't fit.
+    pub fn get(&self, uuid: &Uuid) -> Result<Handle<Resource>, ResourceLoadError> {
+        // The database isn't locked while the resource loads, as it loads the resources it
+        // refers to.
+        let entry = self
+            .state()
+            .resources
+            .get(uuid)
+            .cloned()
+            .ok_or(ResourceLoadError::DoesNotExist)?;
+        if let Some(existing_data) = entry.data.get_cloned() {
+            self.touch(&entry);
+            return Ok(existing_data);
+        }
+        if entry.missing {
+            return Err(ResourceLoadError::Missing);
+        }
+        // Decoding a resource loads the formats it uses, and those may refer back to the
+        // resource itself. Those loads get the resource as it was read, which is enough to tell
+        // what kind of resource it is, instead of loading it again forever.
+        if let Some((loader, loading)) = entry.loading.get_cloned()
+            && loader == thread::current().id()
+        {
+            return Ok(loading);
+        }
+
+        let mut resource = match &entry.plugin {
+            Some(plugin_id) => self.load_plugin_resource(&entry.path, plugin_id)?,
+            None => Resource::load(&entry.path)?,
+        };
+        entry.loading.set(Some((
+            thread::current().id(),
+            Handle::new(resource.clone()),
+        )));
+        let decoded = resource.decode(self);
+        entry.loading.set(None);
+        decoded?;
+
+        // The handle is only published once the resource is decoded.
+        let loaded_data = {
+            let mut data = entry.data.lock_mut();
+            // Another thread may have loaded the resource in the meantime.
+            if let Some(existing_data) = &*data {
+                return Ok(existing_data.clone());
+            }
+            let loaded_data = Handle::new(resource);
+            *data = Some(loaded_data.clone());
+            loaded_data
+        };
+        self.record_load(&entry);
+        Ok(loaded_data)
+    }
+
+    /// Loads a resource from disk (as contributed by a plugin, if given) and decodes it.
+    fn read<P: AsRef<Path>>(
+        &self,
+        path: P,
+        plugin_id: Option<&str>,
+    ) -> Result<Resource, ResourceLoadError> {
+        let mut resource = match plugin_id {
+            Some(plugin_id) => self.load_plugin_resource(path, plugin_id)?,
+            None => Resource::load(path)?,
+        };
+        resource.decode(self)?;
+        Ok(resource)
+    }
 }
 
-pub type Handle<T> = Mutable<T>;
+/// A handle to a loaded value, which every clone shares: changes made through one handle are
+/// seen by (and can be observed from) all of them.
+///
+/// The database keeps track of how many handles to a resource are left, to know when it can be
+/// unloaded. The value is only reachable through the handle (rather than through the [`Mutable`]
+/// it's kept in), so that nothing can hold on to it without being counted.
+#[derive(Debug, Clone)]
+pub struct Handle<T> {
+    value: Mutable<T>,
+    /// Shared by every clone, so that they can be counted.
+    users: Arc<()>,
+}
+
+impl<T> Handle<T> {
+    /// Creates the first handle to a value.
+    pub fn new(value: T) -> Self {
+        Handle {
+            value: Mutable::new(value),
+            users: Arc::new(()),
+        }
+    }
+
+    /// Returns how many handles share the value.
+    pub fn count(&self) -> usize {
+        Arc::strong_count(&self.users)
+    }
+
+    /// Locks the value for reading.
+    pub fn lock_ref(&self) -> MutableLockRef<'_, T> {
+        self.value.lock_ref()
+    }
+
+    /// Locks the value for writing; its signals see the change once it's unlocked.
+    pub fn lock_mut(&self) -> MutableLockMut<'_, T> {
+        self.value.lock_mut()
+    }
+
+    /// Replaces the value, letting its signals know.
+    pub fn set(&self, value: T) {
+        self.value.set(value);
+    }
+
+    /// Returns a signal of what a function makes of the value, every time it changes.
+    pub fn signal_ref<B, F: FnMut(&T) -> B>(&self, f: F) -> MutableSignalRef<T, F> {
+        self.value.signal_ref(f)
+    }
+}
+
+impl<T: Clone> Handle<T> {
+    /// Returns a signal of a copy of the value, every time it changes.
+    pub fn signal_cloned(&self) -> MutableSignalCloned<T> {
+        self.value.signal_cloned()
+    }
+}
 
 /// An entry of a resource as saved on disk (or nested in another resource).
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct ResourceEntry {
     path: PathBuf,
+    /// The loaded resource, shared with everyone who asked for it.
     #[serde(skip)]
-    data: Option<Handle<Resource>>,
+    data: Mutable<Option<Handle<Resource>>>,
+    /// The resource as it was read, while a thread is decoding it.
+    #[serde(skip)]
+    loading: Mutable<Option<(ThreadId, Handle<Resource>)>>,
+    /// The id of the plugin the resource comes from, if it isn't the project's own.
+    #[serde(skip)]
+    plugin: Option<String>,
+    /// Whether the file of the resource was removed.
+    #[serde(skip)]
+    missing: bool,
+    /// How much the loaded resource weighs, and when it was last used.
+    #[serde(skip)]
+    usage: Arc<cache::Usage>,
 }
 
 /// A temporary sample of a resource as the project is gathering UUIDs.
@@ -201,24 +603,13 @@ pub struct ResourceSample {
     uuid: Uuid,
 }
 
-impl ResourceEntry {
-    /// Returns a safe, hot-reloadable reference to some resource.
-    pub fn get_ref(&mut self) -> Result<Handle<Resource>, ResourceLoadError> {
-        match &self.data {
-            Some(existing_data) => Ok(existing_data.clone()),
-            None => {
-                let loaded_data = Handle::new(Resource::load(&self.path)?);
-                self.data = Some(loaded_data.clone());
-                Ok(loaded_data)
-            }
-        }
-    }
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Resource {
     #[serde(flatten)]
     data: ResourceData,
+    /// Whether the file of the resource was removed after it was loaded.
+    #[serde(skip)]
+    missing: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -228,12 +619,82 @@ pub enum ResourceData {
     ObjectRecipe(ObjectRecipe),
     Room(RoomDescriptor),
     Custom(CustomResourceData),
+    /// A block made out of other blocks, usable by any behaviour of the project.
+    Block(CustomBlockDefinition),
+    /// A behaviour that can be given to objects.
+    Behaviour(BehaviourDescriptor),
 }
 
+/// A resource made by the user, holding data in a format of their own (like an Item or an Enemy).
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CustomResourceData {}
+pub struct CustomResourceData {
+    /// The [`FormatDefinition`] the data follows.
+    format: ExternalResource,
+    /// The data, as written in the resource file.
+    data: serde_json::Value,
+    /// The data read as a value of its format, once the resource is decoded.
+    #[serde(skip)]
+    value: Option<VariantValue>,
+}
+
+impl CustomResourceData {
+    /// Creates custom data of a format; it still has to be decoded before its value can be used.
+    pub fn new(format: Uuid, data: serde_json::Value) -> Self {
+        CustomResourceData {
+            format: ExternalResource {
+                uuid: format,
+                handle: None,
+            },
+            data,
+            value: None,
+        }
+    }
+
+    /// Returns the UUID of the format definition the data follows.
+    pub fn format(&self) -> Uuid {
+        self.format.uuid
+    }
+
+    /// Returns the data as written in the resource file.
+    pub fn data(&self) -> &serde_json::Value {
+        &self.data
+    }
+
+    /// Returns the data as a value of its format, if it was decoded.
+    pub fn value(&self) -> Option<&VariantValue> {
+        self.value.as_ref()
+    }
+
+    /// Checks the data against its format and reads it as a value.
+    pub fn decode(&mut self, database: &ResourceDatabase) -> Result<(), Vec<ValidationError>> {
+        let format = Format::External(self.format.clone());
+        self.value = Some(decode_json(&self.data, &format, database)?);
+        Ok(())
+    }
+}
 
 impl Resource {
+    /// Returns the data of this resource.
+    pub fn data(&self) -> &ResourceData {
+        &self.data
+    }
+
+    /// Returns whether the file of this resource was removed. The resource keeps the data it
+    /// had when it was last loaded.
+    pub fn is_missing(&self) -> bool {
+        self.missing
+    }
+
+    /// Prepares the data of this resource for use, like checking custom data against its format.
+    pub fn decode(&mut self, database: &ResourceDatabase) -> Result<(), ResourceLoadError> {
+        match &mut self.data {
+            ResourceData::Custom(custom) => {
+                custom.decode(database).map_err(ResourceLoadError::Invalid)
+            }
+            _ => Ok(()),
+        }
+    }
+
     /// Loads the resource from disk.
     pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ResourceLoadError>
     where
@@ -284,3 +745,69 @@ pub struct EmbeddedResource {
     pub uuid: Uuid,
     pub resource: Resource,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const FORMAT: &str = "0d6e1b2a-8f3c-4a57-9e21-5c7b4d3f8a10";
+    const LINKED: &str = "4b8e2f61-a9d3-4c75-8b1e-7f0c6d2e5a93";
+    const BROKEN: &str = "c2a7e5d8-1f4b-4e96-a3c0-8d5f9b1e7a24";
+
+    fn custom(uuid: &str, data: serde_json::Value) -> serde_json::Value {
+        json!({
+            "uuid": uuid,
+            "type": "Custom",
+            "data": { "format": { "uuid": FORMAT }, "data": data }
+        })
+    }
+
+    #[test]
+    fn resources_are_only_handed_out_once_decoded() {
+        let temp = tempfile::tempdir().unwrap();
+        let files = [
+            (
+                "link.json",
+                json!({
+                    "uuid": FORMAT,
+                    "type": "Format",
+                    "data": {
+                        "name": "Link",
+                        "type": "tuple",
+                        "content": [
+                            ["name", { "type": "base_type", "content": "text" }],
+                            ["next", { "type": "maybe", "content": { "type": "external", "content": { "uuid": LINKED } } }]
+                        ]
+                    }
+                }),
+            ),
+            // Its format refers to the resource itself.
+            (
+                "linked.json",
+                custom(LINKED, json!({ "name": "a", "next": { "uuid": LINKED } })),
+            ),
+            (
+                "broken.json",
+                custom(BROKEN, json!({ "name": 3, "next": null })),
+            ),
+        ];
+        for (name, json) in files {
+            std::fs::write(temp.path().join(name), json.to_string()).unwrap();
+        }
+        let database = ResourceDatabase::from_directory(temp.path().to_path_buf());
+
+        let linked = database.get(&LINKED.parse().unwrap()).unwrap();
+        let ResourceData::Custom(custom) = linked.lock_ref().data().clone() else {
+            panic!("expected custom data");
+        };
+        assert!(custom.value().is_some());
+
+        for _ in 0..2 {
+            assert!(matches!(
+                database.get(&BROKEN.parse().unwrap()),
+                Err(ResourceLoadError::Invalid(_))
+            ));
+        }
+    }
+}
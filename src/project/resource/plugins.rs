@@ -0,0 +1,115 @@
+//! Resources contributed by plugins.
+//!
+//! The resources of active plugins are merged into the [`ResourceDatabase`] of a project under
+//! [`namespaced_uuid`]s. As they load, every reference a plugin resource makes to another
+//! resource of the same plugin is rewritten to the namespaced UUID, so plugins don't need to
+//! know how projects see them. Plugin resources are read-only.
+
+use super::{Resource, ResourceDatabase, ResourceEntry, ResourceLoadError};
+use crate::plugin::{PluginRef, is_inside_folder, namespaced_uuid};
+use serde_json::Value;
+use std::{collections::HashSet, path::Path};
+use uuid::Uuid;
+
+impl ResourceDatabase {
+    /// Adds the resources contributed by a plugin, if it's active.
+    ///
+    /// Resource files that can't be sampled, or that aren't inside of the plugin's folder, are
+    /// skipped.
+    pub fn add_plugin(&self, plugin: &PluginRef) {
+        if !plugin.is_active() {
+            return;
+        }
+        let plugin_id = plugin.metadata().id();
+        self.remove_plugin(plugin_id);
+
+        let mut state = self.state_mut();
+        let mut local_uuids = HashSet::new();
+        for path in plugin.metadata().contributions().resources() {
+            if !is_inside_folder(path) {
+                continue;
+            }
+            let path = plugin.path().join(path);
+            let Some(sample) = Self::get_sample(&path) else {
+                continue;
+            };
+            local_uuids.insert(sample.uuid);
+            state.resources.insert(
+                namespaced_uuid(plugin_id, &sample.uuid),
+                ResourceEntry {
+                    path,
+                    plugin: Some(plugin_id.to_string()),
+                    ..Default::default()
+                },
+            );
+        }
+        state.plugins.insert(plugin_id.to_string(), local_uuids);
+    }
+
+    /// Removes the resources contributed by a plugin.
+    ///
+    /// Handles to its resources that were already given out keep working.
+    pub fn remove_plugin(&self, plugin_id: &str) {
+        let mut state = self.state_mut();
+        if let Some(local_uuids) = state.plugins.remove(plugin_id) {
+            for uuid in local_uuids {
+                state.resources.remove(&namespaced_uuid(plugin_id, &uuid));
+            }
+        }
+    }
+
+    /// Returns the id of the plugin a resource comes from, if it isn't the project's own.
+    pub fn plugin_of(&self, uuid: &Uuid) -> Option<String> {
+        self.state().resources.get(uuid)?.plugin.clone()
+    }
+
+    /// Loads a resource contributed by a plugin from disk, namespacing the UUIDs it refers to.
+    pub(super) fn load_plugin_resource<P: AsRef<Path>>(
+        &self,
+        path: P,
+        plugin_id: &str,
+    ) -> Result<Resource, ResourceLoadError> {
+        Ok(serde_json::from_value(
+            self.read_plugin_json(path, plugin_id)?,
+        )?)
+    }
+
+    /// Reads the file of a resource contributed by a plugin, namespacing the UUIDs it refers to.
+    pub(super) fn read_plugin_json<P: AsRef<Path>>(
+        &self,
+        path: P,
+        plugin_id: &str,
+    ) -> Result<Value, ResourceLoadError> {
+        let text = std::fs::read_to_string(path)?;
+        let mut value: Value = serde_json::from_str(&text)?;
+        if let Some(local_uuids) = self.state().plugins.get(plugin_id) {
+            namespace_references(&mut value, plugin_id, local_uuids);
+        }
+        Ok(value)
+    }
+}
+
+/// Rewrites every string of a value that is a UUID of the plugin (or a `custom:` block source
+/// made of one) to its namespaced UUID.
+fn namespace_references(value: &mut Value, plugin_id: &str, local_uuids: &HashSet<Uuid>) {
+    match value {
+        Value::String(text) => {
+            let (prefix, uuid) = match text.strip_prefix("custom:") {
+                Some(uuid) => ("custom:", uuid),
+                None => ("", text.as_str()),
+            };
+            if let Ok(uuid) = Uuid::parse_str(uuid)
+                && local_uuids.contains(&uuid)
+            {
+                *text = format!("{prefix}{}", namespaced_uuid(plugin_id, &uuid));
+            }
+        }
+        Value::Array(values) => values
+            .iter_mut()
+            .for_each(|value| namespace_references(value, plugin_id, local_uuids)),
+        Value::Object(fields) => fields
+            .values_mut()
+            .for_each(|value| namespace_references(value, plugin_id, local_uuids)),
+        Value::Null | Value::Bool(_) | Value::Number(_) => {}
+    }
+}
@@ -0,0 +1,266 @@
+//! Hot reloading of resources.
+//!
+//! A [`ResourceWatcher`] watches over the folder of a project from a thread of its own, and
+//! updates the [`ResourceDatabase`] whenever files are created, changed, moved or removed.
+//! Editors save files in bursts (writing to a temporary file, renaming it, touching it...), so
+//! files are only looked at once they've been left alone for [`DEBOUNCE`] -- or, if they never
+//! are, once they've been changing for [`MAX_DEBOUNCE`].
+//!
+//! Whoever wants to know about changes, like the editor or a running game, can
+//! [`subscribe`](ResourceDatabase::subscribe) to [`ResourceEvent`]s.
+
+use super::{ResourceDatabase, ResourceLoadError};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher as _};
+use ordermap::OrderSet;
+use std::{
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::RecvTimeoutError,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+use uuid::Uuid;
+use walkdir::WalkDir;
+
+/// How long files must be left alone before their changes are picked up.
+pub const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// How long changes can wait to be picked up while files keep changing.
+pub const MAX_DEBOUNCE: Duration = Duration::from_secs(1);
+
+/// A change made to the resources of a database.
+#[derive(Debug, Clone)]
+pub enum ResourceEvent {
+    /// A file with a new resource was found, or the file of a missing resource is back.
+    Created { uuid: Uuid, path: PathBuf },
+    /// The file of a resource changed. If the resource was loaded, it was reloaded, so its
+    /// handles have a new value.
+    Modified { uuid: Uuid },
+    /// The file of a resource was moved.
+    Moved {
+        uuid: Uuid,
+        from: PathBuf,
+        to: PathBuf,
+    },
+    /// The file of a resource was removed. Its handles keep their last value, and the resource
+    /// is kept as missing, unless it was [deleted](ResourceDatabase::delete).
+    Removed { uuid: Uuid, path: PathBuf },
+    /// A file claims the UUID of another resource, so it's ignored.
+    Duplicated { uuid: Uuid, path: PathBuf },
+    /// The file of a loaded resource changed, but it couldn't be reloaded, so its handles keep
+    /// their value.
+    ReloadFailed {
+        uuid: Uuid,
+        error: Arc<ResourceLoadError>,
+    },
+}
+
+impl ResourceEvent {
+    /// Returns the UUID of the resource that changed.
+    pub fn uuid(&self) -> Uuid {
+        match self {
+            ResourceEvent::Created { uuid, .. }
+            | ResourceEvent::Modified { uuid }
+            | ResourceEvent::Moved { uuid, .. }
+            | ResourceEvent::Removed { uuid, .. }
+            | ResourceEvent::Duplicated { uuid, .. }
+            | ResourceEvent::ReloadFailed { uuid, .. } => *uuid,
+        }
+    }
+}
+
+/// Watches over a folder on a thread of its own, updating a database as files change.
+///
+/// The watch stops when the watcher is dropped.
+pub struct ResourceWatcher {
+    watcher: Option<RecommendedWatcher>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl std::fmt::Debug for ResourceWatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ResourceWatcher").finish_non_exhaustive()
+    }
+}
+
+impl Drop for ResourceWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.watcher.take();
+        if let Some(thread) = self.thread.take() {
+            // The thread doesn't panic, and there's nothing to do if it did.
+            let _ = thread.join();
+        }
+    }
+}
+
+impl ResourceDatabase {
+    /// Watches over a directory from a background thread, and updates resources whenever
+    /// they change, until the returned watcher is dropped.
+    pub fn watch(&self, base_path: PathBuf) -> Result<ResourceWatcher, notify::Error> {
+        let (tx, rx) = std::sync::mpsc::channel::<notify::Result<notify::Event>>();
+        let mut watcher = notify::recommended_watcher(tx)?;
+        watcher.watch(base_path.as_path(), RecursiveMode::Recursive)?;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let database = self.clone();
+            let stop = stop.clone();
+            std::thread::spawn(move || {
+                let mut pending = OrderSet::new();
+                let mut first_change = Instant::now();
+                let mut last_change = Instant::now();
+                while !stop.load(Ordering::Relaxed) {
+                    match rx.recv_timeout(DEBOUNCE) {
+                        Ok(Ok(event)) if !matches!(event.kind, EventKind::Access(_)) => {
+                            if pending.is_empty() {
+                                first_change = Instant::now();
+                            }
+                            pending.extend(event.paths);
+                            last_change = Instant::now();
+                        }
+                        Ok(Ok(_)) => {}
+                        // TODO: Use `tracing` for this.
+                        Ok(Err(e)) => {
+                            eprintln!("Error watching resources: {} {}", base_path.display(), e)
+                        }
+                        Err(RecvTimeoutError::Timeout) => {}
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                    let settled =
+                        last_change.elapsed() >= DEBOUNCE || first_change.elapsed() >= MAX_DEBOUNCE;
+                    if !pending.is_empty() && settled {
+                        database.apply_changes(std::mem::take(&mut pending));
+                    }
+                }
+            })
+        };
+
+        Ok(ResourceWatcher {
+            watcher: Some(watcher),
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    /// Updates the database after some files or folders changed.
+    fn apply_changes(&self, paths: OrderSet<PathBuf>) {
+        // Files that are there are looked at first, so that resources that moved aren't taken
+        // as removed.
+        let (present, missing): (Vec<_>, Vec<_>) = paths.into_iter().partition(|p| p.exists());
+        for path in present {
+            if path.is_dir() {
+                WalkDir::new(path)
+                    .into_iter()
+                    .filter_map(|entry| entry.ok())
+                    .filter(|entry| entry.file_type().is_file())
+                    .for_each(|entry| self.patch_entry_from_path(entry.into_path()));
+            } else {
+                self.patch_entry_from_path(path);
+            }
+        }
+        for path in missing {
+            self.forget_path(&path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::resource::ResourceData;
+    use serde_json::json;
+    use std::{path::Path, sync::mpsc::Receiver};
+
+    const UUID: &str = "e3b1c9d2-5a47-4f68-9c0e-1d2f3a4b5c6d";
+
+    fn write_format(path: &Path, name: &str) {
+        let format = json!({
+            "uuid": UUID,
+            "type": "Format",
+            "data": { "name": name, "type": "tuple", "content": [] }
+        });
+        std::fs::write(path, format.to_string()).unwrap();
+    }
+
+    fn events(receiver: &Receiver<ResourceEvent>) -> Vec<String> {
+        receiver
+            .try_iter()
+            .map(|event| match event {
+                ResourceEvent::Created { .. } => "created",
+                ResourceEvent::Modified { .. } => "modified",
+                ResourceEvent::Moved { .. } => "moved",
+                ResourceEvent::Removed { .. } => "removed",
+                ResourceEvent::Duplicated { .. } => "duplicated",
+                ResourceEvent::ReloadFailed { .. } => "reload failed",
+            })
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn applies_created_modified_and_renamed_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let database = ResourceDatabase::from_directory(temp.path().to_path_buf());
+        let receiver = database.subscribe();
+        let uuid = UUID.parse().unwrap();
+        let path = temp.path().join("item.json");
+
+        write_format(&path, "Item");
+        database.apply_changes(OrderSet::from([path.clone()]));
+        assert_eq!(events(&receiver), ["created"]);
+        let handle = database.get(&uuid).unwrap();
+
+        write_format(&path, "Thing");
+        database.apply_changes(OrderSet::from([path.clone()]));
+        assert_eq!(events(&receiver), ["modified"]);
+        let ResourceData::Format(format) = handle.lock_ref().data().clone() else {
+            panic!("expected a format");
+        };
+        assert_eq!(format.name(), "Thing");
+
+        let new_path = temp.path().join("thing.json");
+        std::fs::rename(&path, &new_path).unwrap();
+        database.apply_changes(OrderSet::from([path, new_path.clone()]));
+        assert_eq!(events(&receiver), ["moved", "modified"]);
+        assert!(!handle.lock_ref().is_missing());
+        assert_eq!(database.uuids(), [uuid]);
+    }
+
+    #[test]
+    fn applies_removed_files_and_promotes_duplicates() {
+        let temp = tempfile::tempdir().unwrap();
+        let first = temp.path().join("a.json");
+        let second = temp.path().join("b.json");
+        write_format(&first, "First");
+        write_format(&second, "Second");
+        let database = ResourceDatabase::from_directory(temp.path().to_path_buf());
+        let receiver = database.subscribe();
+        let uuid = UUID.parse().unwrap();
+        assert_eq!(
+            database.duplicates()[0].ignored,
+            std::slice::from_ref(&second)
+        );
+        let handle = database.get(&uuid).unwrap();
+
+        // The duplicate is read as the resource once the file it duplicated is gone.
+        std::fs::remove_file(&first).unwrap();
+        database.apply_changes(OrderSet::from([first]));
+        assert_eq!(events(&receiver), ["moved", "modified"]);
+        assert!(database.duplicates().is_empty());
+        let ResourceData::Format(format) = handle.lock_ref().data().clone() else {
+            panic!("expected a format");
+        };
+        assert_eq!(format.name(), "Second");
+
+        std::fs::remove_file(&second).unwrap();
+        database.apply_changes(OrderSet::from([second]));
+        assert_eq!(events(&receiver), ["removed"]);
+        assert!(handle.lock_ref().is_missing());
+        assert!(database.uuids().is_empty());
+    }
+}
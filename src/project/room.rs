@@ -14,6 +14,17 @@ pub struct RoomDescriptor {
     layers: Vec<RoomLayer>,
 }
 
+impl RoomDescriptor {
+    /// Creates a room without any layers.
+    pub fn new(name: String) -> Self {
+        RoomDescriptor {
+            name,
+            description: None,
+            layers: Vec::new(),
+        }
+    }
+}
+
 /// A Layer of a Room.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RoomLayer {
@@ -46,7 +57,7 @@ pub struct RoomInstance<'game> {
     layers: Vec<RoomLayer>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Serialize)]
 pub struct ObjectLayerInstance<'game> {
     objects: Vec<ObjectInstance<'game>>,
 }
@@ -1,5 +1,19 @@
-use super::Project;
-use crate::screen::ScreenInstance;
+use super::{
+    Project,
+    object::{ObjectInstance, ObjectInstanceDescriptor},
+    resource::{ResourceData, ResourceEvent, ResourceLoadError},
+};
+use crate::{
+    behaviour::{
+        BehaviourInstance, ExecutionContext, ReifyContext, ReifyError, RuntimeError, event::Event,
+        variables::Variables,
+    },
+    format::VariantValue,
+    screen::ScreenInstance,
+};
+use std::sync::mpsc::Receiver;
+use thiserror::Error;
+use uuid::Uuid;
 
 /// A game that is currently running.
 ///
@@ -9,21 +23,133 @@ use crate::screen::ScreenInstance;
 pub struct Game<'game> {
     pub project: &'game Project,
     pub current_scene: Option<ScreenInstance<'game>>,
+    /// The story state: the global variables declared by the project's story definition.
+    pub story: Variables,
+    /// The project's startup behaviour, once the game has started.
+    pub behaviour: Option<BehaviourInstance<'game>>,
+    /// The objects in the game, in the order they were spawned.
+    pub objects: Vec<ObjectInstance<'game>>,
+    /// Changes made to the project's resources since the game was created.
+    resource_events: Receiver<ResourceEvent>,
+}
+
+/// Error for when a behaviour the game was running failed.
+#[derive(Debug, Error)]
+pub enum GameError {
+    /// The behaviour could not be built from its descriptor.
+    #[error("Failed to build a behaviour: {0:?}")]
+    Reify(ReifyError<'static>),
+    /// The behaviour failed while it was running.
+    #[error("A behaviour failed while running: {0}")]
+    Runtime(#[from] RuntimeError),
+    /// The recipe of an object could not be loaded.
+    #[error("Failed to load the object recipe {uuid}: {error}")]
+    UnloadableRecipe {
+        uuid: Uuid,
+        error: ResourceLoadError,
+    },
+    /// An object was to be baked from a resource that isn't an object recipe.
+    #[error("The resource {0} is not an object recipe")]
+    NotARecipe(Uuid),
+}
+
+impl From<ReifyError<'static>> for GameError {
+    fn from(value: ReifyError<'static>) -> Self {
+        GameError::Reify(value)
+    }
 }
 
 impl<'game> Game<'game> {
     /// Reifies a game from a project. This only _creates_
     /// an instace of a game with appropriate handles to resources, etc,
     /// it doesn't make the game start playing.
-    pub fn from_project(project: &Project) -> Game {
+    pub fn from_project(project: &Project) -> Game<'_> {
         Game {
             project,
             current_scene: None,
+            story: project.story_variables(),
+            behaviour: None,
+            objects: Vec::new(),
+            resource_events: project.resource_database.subscribe(),
         }
     }
 
+    /// Returns the changes made to the project's resources (while it's being watched) since
+    /// the last time this was called.
+    ///
+    /// Handles to resources are already up to date; this is for the parts of the game that
+    /// were built out of resources to rebuild themselves.
+    pub fn resource_changes(&self) -> impl Iterator<Item = ResourceEvent> + '_ {
+        self.resource_events.try_iter()
+    }
+
     /// Calls the project's startup behaviour to set up and finally begin to play the game.
-    pub fn game_started(&mut self) {
-        self.project.startup_behaviour.reify().execute();
+    pub fn game_started(&mut self) -> Result<(), GameError> {
+        let project = self.project;
+        let ctx = ReifyContext::for_project(project);
+        self.behaviour = Some(project.startup_behaviour.reify(&ctx)?);
+        self.dispatch(&Event::Created, VariantValue::Void)?;
+        Ok(())
+    }
+
+    /// Bakes an object from its recipe, lets it know it was created, and adds it to the game.
+    pub fn spawn_object(&mut self, descriptor: ObjectInstanceDescriptor) -> Result<(), GameError> {
+        let project = self.project;
+        let uuid = descriptor.recipe().uuid;
+        let handle = project
+            .resource_database
+            .get(&uuid)
+            .map_err(|error| GameError::UnloadableRecipe { uuid, error })?;
+        let resource = handle.lock_ref();
+        let ResourceData::ObjectRecipe(recipe) = resource.data() else {
+            return Err(GameError::NotARecipe(uuid));
+        };
+        let mut object =
+            ObjectInstance::new(descriptor, recipe, &ReifyContext::for_project(project))?;
+        drop(resource);
+        self.dispatch_to_object(&mut object, &Event::Created, VariantValue::Void)?;
+        self.objects.push(object);
+        Ok(())
+    }
+
+    /// Sends an event to the project's startup behaviour, then to every object in the game (in
+    /// the order they were spawned), running the handlers that respond to it.
+    ///
+    /// Objects spawned while the event is being handled don't receive it.
+    pub fn dispatch(&mut self, event: &Event, payload: VariantValue) -> Result<(), RuntimeError> {
+        // Behaviours are taken out of the game while they run, so they can change the game freely.
+        if let Some(behaviour) = self.behaviour.take() {
+            let result =
+                behaviour.dispatch(event, payload.clone(), &mut ExecutionContext::new(self));
+            self.behaviour = Some(behaviour);
+            result?;
+        }
+
+        let mut objects = std::mem::take(&mut self.objects);
+        let result = objects
+            .iter_mut()
+            .try_for_each(|object| self.dispatch_to_object(object, event, payload.clone()));
+        let spawned = std::mem::replace(&mut self.objects, objects);
+        self.objects.extend(spawned);
+        result
+    }
+
+    /// Sends an event to the behaviour of an object that is out of [`Game::objects`].
+    fn dispatch_to_object(
+        &mut self,
+        object: &mut ObjectInstance<'game>,
+        event: &Event,
+        payload: VariantValue,
+    ) -> Result<(), RuntimeError> {
+        let Some(behaviour) = object.behaviour.take() else {
+            return Ok(());
+        };
+        let result = behaviour.dispatch(
+            event,
+            payload,
+            &mut ExecutionContext::new(self).with_object(object),
+        );
+        object.behaviour = Some(behaviour);
+        result
     }
 }